@@ -5,4 +5,5 @@ cargo +stable build --target wasm32-unknown-unknown --release
 cp target/wasm32-unknown-unknown/release/sputnik_staking.wasm ./sputnik-staking/res/
 cp target/wasm32-unknown-unknown/release/sputnikdao2.wasm ./sputnikdao2/res/
 cp target/wasm32-unknown-unknown/release/sputnikdao_factory2.wasm ./sputnikdao-factory2/res/
-cp target/wasm32-unknown-unknown/release/test_token.wasm ./test-token/res/
\ No newline at end of file
+cp target/wasm32-unknown-unknown/release/test_token.wasm ./test-token/res/
+cp target/wasm32-unknown-unknown/release/test_nft.wasm ./test-nft/res/
@@ -0,0 +1,75 @@
+//! Structured event logs following NEP-297.
+//! Every event is logged as a single line:
+//! `EVENT_JSON:{"standard":"sputnik-staking","version":"1.0.0","event":"<name>","data":{...}}`.
+
+use near_sdk::json_types::U128;
+use near_sdk::serde::Serialize;
+use near_sdk::{env, serde_json, AccountId};
+
+/// Name of the event standard emitted by this contract.
+pub const EVENT_STANDARD: &str = "sputnik-staking";
+
+/// Version of the events schema. Must be bumped on any change of the payloads.
+pub const EVENT_STANDARD_VERSION: &str = "1.0.0";
+
+/// Events emitted by the staking contract.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum Event<'a> {
+    /// User registered storage in this contract.
+    UserRegistered { account_id: &'a AccountId },
+    /// User removed their storage from this contract.
+    UserUnregistered { account_id: &'a AccountId },
+    /// Vote tokens were deposited.
+    Deposit {
+        account_id: &'a AccountId,
+        amount: U128,
+    },
+    /// Vote tokens were withdrawn.
+    Withdraw {
+        account_id: &'a AccountId,
+        amount: U128,
+    },
+    /// Withdrawal of vote tokens failed and was reverted.
+    WithdrawReverted {
+        account_id: &'a AccountId,
+        amount: U128,
+    },
+    /// Votes were delegated from `account_id` to `delegate_id`.
+    Delegate {
+        account_id: &'a AccountId,
+        delegate_id: &'a AccountId,
+        amount: U128,
+    },
+    /// Votes were undelegated from `delegate_id` back to `account_id`.
+    Undelegate {
+        account_id: &'a AccountId,
+        delegate_id: &'a AccountId,
+        amount: U128,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog<'a> {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event: &'a Event<'a>,
+}
+
+impl Event<'_> {
+    /// Logs the event in NEP-297 format.
+    pub fn emit(&self) {
+        let log = EventLog {
+            standard: EVENT_STANDARD,
+            version: EVENT_STANDARD_VERSION,
+            event: self,
+        };
+        env::log_str(&format!(
+            "EVENT_JSON:{}",
+            serde_json::to_string(&log).expect("INTERNAL_FAIL")
+        ));
+    }
+}
@@ -10,6 +10,9 @@ use near_sdk::{
 
 pub use user::{User, VersionedUser};
 
+use crate::events::Event;
+
+pub mod events;
 mod storage_impl;
 mod user;
 
@@ -89,7 +92,13 @@ impl Contract {
     /// If enough tokens and storage, forwards this to owner account.
     pub fn delegate(&mut self, account_id: AccountId, amount: U128) -> Promise {
         let sender_id = env::predecessor_account_id();
-        self.internal_delegate(sender_id, account_id.clone().into(), amount.0);
+        self.internal_delegate(sender_id.clone(), account_id.clone().into(), amount.0);
+        Event::Delegate {
+            account_id: &sender_id,
+            delegate_id: &account_id,
+            amount,
+        }
+        .emit();
         ext_sputnik::delegate(
             account_id.into(),
             amount,
@@ -102,7 +111,13 @@ impl Contract {
     /// Remove given amount of delegation.
     pub fn undelegate(&mut self, account_id: AccountId, amount: U128) -> Promise {
         let sender_id = env::predecessor_account_id();
-        self.internal_undelegate(sender_id, account_id.clone().into(), amount.0);
+        self.internal_undelegate(sender_id.clone(), account_id.clone().into(), amount.0);
+        Event::Undelegate {
+            account_id: &sender_id,
+            delegate_id: &account_id,
+            amount,
+        }
+        .emit();
         ext_sputnik::undelegate(
             account_id.into(),
             amount,
@@ -117,6 +132,11 @@ impl Contract {
     pub fn withdraw(&mut self, amount: U128) -> Promise {
         let sender_id = env::predecessor_account_id();
         self.internal_withdraw(&sender_id, amount.0);
+        Event::Withdraw {
+            account_id: &sender_id,
+            amount,
+        }
+        .emit();
         ext_fungible_token::ft_transfer(
             sender_id.clone(),
             amount,
@@ -147,6 +167,11 @@ impl Contract {
             PromiseResult::Failed => {
                 // This reverts the changes from withdraw function.
                 self.internal_deposit(&sender_id, amount.0);
+                Event::WithdrawReverted {
+                    account_id: &sender_id,
+                    amount,
+                }
+                .emit();
             }
         };
     }
@@ -167,6 +192,11 @@ impl FungibleTokenReceiver for Contract {
         );
         assert!(msg.is_empty(), "ERR_INVALID_MESSAGE");
         self.internal_deposit(&sender_id, amount.0);
+        Event::Deposit {
+            account_id: &sender_id,
+            amount,
+        }
+        .emit();
         PromiseOrValue::Value(U128(0))
     }
 }
@@ -175,7 +205,7 @@ impl FungibleTokenReceiver for Contract {
 mod tests {
     use near_contract_standards::storage_management::StorageManagement;
     use near_sdk::json_types::U64;
-    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::test_utils::{accounts, get_logs, VMContextBuilder};
     use near_sdk::testing_env;
 
     use near_sdk_sim::to_yocto;
@@ -234,4 +264,44 @@ mod tests {
         assert_eq!(user.delegated_amount(), 0);
         assert_eq!(user.next_action_timestamp, U64(UNSTAKE_PERIOD));
     }
+
+    #[test]
+    fn test_events() {
+        let contract_owner: AccountId = accounts(0);
+        let voting_token: AccountId = accounts(1);
+        let user: AccountId = accounts(2);
+
+        let mut context = VMContextBuilder::new();
+        testing_env!(context
+            .predecessor_account_id(contract_owner.clone())
+            .build());
+        let mut contract = Contract::new(contract_owner, voting_token.clone(), U64(1000));
+
+        testing_env!(context.attached_deposit(to_yocto("1")).build());
+        contract.storage_deposit(Some(user.clone()), None);
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"standard":"sputnik-staking","version":"1.0.0","event":"user_registered","data":{"account_id":"charlie"}}"#
+            ]
+        );
+
+        testing_env!(context.predecessor_account_id(voting_token).build());
+        contract.ft_on_transfer(user.clone(), U128(100), "".to_string());
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"standard":"sputnik-staking","version":"1.0.0","event":"deposit","data":{"account_id":"charlie","amount":"100"}}"#
+            ]
+        );
+
+        testing_env!(context.predecessor_account_id(user.clone()).build());
+        contract.delegate(user, U128(40));
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"standard":"sputnik-staking","version":"1.0.0","event":"delegate","data":{"account_id":"charlie","delegate_id":"charlie","amount":"40"}}"#
+            ]
+        );
+    }
 }
@@ -67,6 +67,10 @@ impl StorageManagement for Contract {
             // TODO: figure out force option logic.
             assert!(user.vote_amount.0 > 0, "ERR_STORAGE_UNREGISTER_NOT_EMPTY");
             self.users.remove(&account_id);
+            Event::UserUnregistered {
+                account_id: &account_id,
+            }
+            .emit();
             Promise::new(account_id.clone()).transfer(user.near_amount.0);
             true
         } else {
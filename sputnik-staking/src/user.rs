@@ -151,6 +151,10 @@ impl Contract {
     pub fn internal_register_user(&mut self, sender_id: &AccountId, near_amount: Balance) {
         let user = User::new(near_amount);
         self.save_user(sender_id, user);
+        Event::UserRegistered {
+            account_id: sender_id,
+        }
+        .emit();
         ext_sputnik::register_delegation(
             sender_id.clone(),
             self.owner_id.clone(),
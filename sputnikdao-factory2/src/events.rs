@@ -0,0 +1,67 @@
+//! Structured event logs following NEP-297.
+//! Every event is logged as a single line:
+//! `EVENT_JSON:{"standard":"sputnikdao-factory","version":"1.0.0","event":"<name>","data":{...}}`.
+
+use near_sdk::json_types::{Base58CryptoHash, U128};
+use near_sdk::serde::Serialize;
+use near_sdk::{env, serde_json, AccountId};
+
+/// Name of the event standard emitted by this contract.
+pub const EVENT_STANDARD: &str = "sputnikdao-factory";
+
+/// Version of the events schema. Must be bumped on any change of the payloads.
+pub const EVENT_STANDARD_VERSION: &str = "1.0.0";
+
+/// Events emitted by the factory.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum Event<'a> {
+    /// New DAO was created and initialized.
+    DaoCreated {
+        dao_id: &'a AccountId,
+        creator_id: &'a AccountId,
+        deposit: U128,
+    },
+    /// DAO creation has failed and the deposit was refunded to the creator.
+    DaoCreationFailed {
+        dao_id: &'a AccountId,
+        creator_id: &'a AccountId,
+        refund: U128,
+    },
+    /// Update of the DAO to the given code was requested.
+    DaoUpdated {
+        dao_id: &'a AccountId,
+        code_hash: &'a Base58CryptoHash,
+    },
+    /// Owner of the factory has changed.
+    OwnerChanged { owner_id: &'a AccountId },
+    /// Default code for new DAOs has changed.
+    DefaultCodeHashChanged { code_hash: &'a Base58CryptoHash },
+    /// Code and its metadata were removed from the factory.
+    ContractDeleted { code_hash: &'a Base58CryptoHash },
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog<'a> {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event: &'a Event<'a>,
+}
+
+impl Event<'_> {
+    /// Logs the event in NEP-297 format.
+    pub fn emit(&self) {
+        let log = EventLog {
+            standard: EVENT_STANDARD,
+            version: EVENT_STANDARD_VERSION,
+            event: self,
+        };
+        env::log_str(&format!(
+            "EVENT_JSON:{}",
+            serde_json::to_string(&log).expect("INTERNAL_FAIL")
+        ));
+    }
+}
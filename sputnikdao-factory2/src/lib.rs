@@ -1,3 +1,4 @@
+pub mod events;
 mod factory_manager;
 
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
@@ -7,6 +8,7 @@ use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::serde_json::{self, json};
 use near_sdk::{env, near_bindgen, AccountId, Balance, CryptoHash, Gas, PanicOnDefault, Promise};
 
+use events::Event;
 use factory_manager::FactoryManager;
 
 type Version = [u8; 2];
@@ -78,6 +80,10 @@ impl SputnikDAOFactory {
     pub fn set_owner(&self, owner_id: AccountId) {
         self.assert_owner();
         env::storage_write(FACTORY_OWNER_KEY, owner_id.as_bytes());
+        Event::OwnerChanged {
+            owner_id: &owner_id,
+        }
+        .emit();
     }
 
     pub fn set_default_code_hash(&self, code_hash: Base58CryptoHash) {
@@ -88,12 +94,20 @@ impl SputnikDAOFactory {
             "Code not found for the given code hash. Please store the code first."
         );
         env::storage_write(DEFAULT_CODE_HASH_KEY, &code_hash);
+        Event::DefaultCodeHashChanged {
+            code_hash: &code_hash.into(),
+        }
+        .emit();
     }
 
     pub fn delete_contract(&self, code_hash: Base58CryptoHash) {
         self.assert_owner();
         self.factory_manager.delete_contract(code_hash);
         self.delete_contract_metadata(code_hash);
+        Event::ContractDeleted {
+            code_hash: &code_hash,
+        }
+        .emit();
     }
 
     #[payable]
@@ -126,8 +140,20 @@ impl SputnikDAOFactory {
     ) -> bool {
         if near_sdk::is_promise_success() {
             self.daos.insert(&account_id);
+            Event::DaoCreated {
+                dao_id: &account_id,
+                creator_id: &predecessor_account_id,
+                deposit: attached_deposit,
+            }
+            .emit();
             true
         } else {
+            Event::DaoCreationFailed {
+                dao_id: &account_id,
+                creator_id: &predecessor_account_id,
+                refund: attached_deposit,
+            }
+            .emit();
             Promise::new(predecessor_account_id).transfer(attached_deposit.0);
             false
         }
@@ -144,6 +170,11 @@ impl SputnikDAOFactory {
             self.daos.contains(&account_id),
             "Must be contract created by factory"
         );
+        Event::DaoUpdated {
+            dao_id: &account_id,
+            code_hash: &code_hash,
+        }
+        .emit();
         self.factory_manager
             .update_contract(account_id, code_hash, "update");
     }
@@ -395,7 +426,7 @@ pub extern "C" fn store() {
 #[cfg(test)]
 mod tests {
     use near_sdk::test_utils::test_env::{alice, bob, carol};
-    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::test_utils::{accounts, get_logs, VMContextBuilder};
     use near_sdk::{testing_env, PromiseResult};
 
     use near_sdk_sim::to_yocto;
@@ -450,6 +481,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_create_events() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context
+            .current_account_id(accounts(0))
+            .predecessor_account_id(accounts(0))
+            .build());
+        let mut factory = SputnikDAOFactory::new();
+
+        testing_env!(
+            context.predecessor_account_id(accounts(0)).build(),
+            near_sdk::VMConfig::test(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])],
+        );
+        factory.on_create("test.alice".parse().unwrap(), U128(10), accounts(1));
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"standard":"sputnikdao-factory","version":"1.0.0","event":"dao_created","data":{"dao_id":"test.alice","creator_id":"bob","deposit":"10"}}"#
+            ]
+        );
+
+        testing_env!(
+            context.predecessor_account_id(accounts(0)).build(),
+            near_sdk::VMConfig::test(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed],
+        );
+        factory.on_create("test2.alice".parse().unwrap(), U128(10), accounts(1));
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"standard":"sputnikdao-factory","version":"1.0.0","event":"dao_creation_failed","data":{"dao_id":"test2.alice","creator_id":"bob","refund":"10"}}"#
+            ]
+        );
+    }
+
     //              #################################              //
     //              #    Factory ownership tests    #              //
     //              #################################              //
@@ -1,23 +1,85 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::json_types::{U128, U64};
 use near_sdk::serde::{Deserialize, Serialize};
-use near_sdk::{env, near_bindgen, AccountId, Promise, PromiseOrValue};
+use near_sdk::{env, log, near_bindgen, AccountId, Promise, PromiseOrValue};
 
+use crate::events::Event;
 use crate::types::{convert_old_to_new_token, OldAccountId};
 use crate::*;
 
+/// Maximum number of pending applications per bounty.
+/// Bounds the gas of refunding them when the bounty is removed.
+pub const MAX_BOUNTY_APPLICATIONS: usize = 20;
+
 /// Information recorded about claim of the bounty by given user.
-#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize)]
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
 #[serde(crate = "near_sdk::serde")]
 pub struct BountyClaim {
     /// Bounty id that was claimed.
-    bounty_id: u64,
+    pub bounty_id: u64,
     /// Start time of the claim.
-    start_time: U64,
+    pub start_time: U64,
     /// Deadline specified by claimer.
-    deadline: U64,
+    pub deadline: U64,
     /// Completed?
-    completed: bool,
+    pub completed: bool,
+}
+
+impl BountyClaim {
+    /// Time after which the claim is expired, unless it's completed.
+    pub fn expires_at(&self) -> u64 {
+        self.start_time.0 + self.deadline.0
+    }
+}
+
+/// How claims on the bounty get assigned, if not first come, first served.
+/// Claims are then applications that start only once approved, either by an `ApproveBountyClaim`
+/// proposal or by a member of `reviewer_role`.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct BountyAssignment {
+    #[serde(default)]
+    pub reviewer_role: Option<String>,
+}
+
+/// Application for a bounty that requires assignment, waiting for review.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct BountyApplication {
+    pub account_id: AccountId,
+    /// Deadline specified by applicant, counted from the approval.
+    pub deadline: U64,
+    /// Bond attached to the application, refunded if it's rejected.
+    pub bond: U128,
+}
+
+/// Changes of the bounty made by `UpdateBounty` proposal. Fields that are not set stay the same.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct BountyUpdate {
+    /// New amount, applies to all payouts from now on. Can't be changed for bounties with milestones.
+    #[serde(default)]
+    pub amount: Option<U128>,
+    /// New max deadline, can only be extended.
+    #[serde(default)]
+    pub max_deadline: Option<U64>,
+    /// Pauses or resumes new claims. Claims in progress are not affected.
+    #[serde(default)]
+    pub paused: Option<bool>,
+}
+
+/// Part of a bounty that is reported and paid out on its own.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct BountyMilestone {
+    pub description: String,
+    /// Share of the bounty's amount paid out when this milestone is done.
+    pub amount: U128,
 }
 
 /// Bounty information.
@@ -36,53 +98,385 @@ pub struct Bounty {
     pub times: u32,
     /// Max deadline from claim that can be spend on this bounty.
     pub max_deadline: U64,
+    /// Milestones that are done one by one, their amounts add up to `amount`.
+    /// If empty, the whole bounty is done at once.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub milestones: Vec<BountyMilestone>,
+    /// If set, claims must be approved before they start.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assignment: Option<BountyAssignment>,
+    /// New claims are not accepted while paused.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub paused: bool,
+}
+
+impl Bounty {
+    /// Amount paid out when given milestone (or the whole bounty) is done.
+    pub fn payout_amount(&self, milestone: Option<u32>) -> Balance {
+        match milestone {
+            Some(milestone) => self.milestones[milestone as usize].amount.0,
+            None => self.amount.0,
+        }
+    }
+
+    /// Funds needed for all the times the bounty can still be done.
+    pub fn total_amount(&self) -> Balance {
+        self.amount
+            .0
+            .checked_mul(self.times as Balance)
+            .expect("ERR_BOUNTY_AMOUNT_OVERFLOW")
+    }
+
+    /// Bounty with the changes applied. Panics if the changes are not valid.
+    pub(crate) fn updated(&self, update: &BountyUpdate) -> Self {
+        let mut bounty = self.clone();
+        if let Some(amount) = update.amount {
+            assert!(self.milestones.is_empty(), "ERR_BOUNTY_MILESTONES_AMOUNT");
+            bounty.amount = amount;
+        }
+        if let Some(max_deadline) = update.max_deadline {
+            assert!(
+                max_deadline.0 >= self.max_deadline.0,
+                "ERR_BOUNTY_WRONG_DEADLINE"
+            );
+            bounty.max_deadline = max_deadline;
+        }
+        if let Some(paused) = update.paused {
+            bounty.paused = paused;
+        }
+        bounty
+    }
+
+    /// Panics if the bounty is not valid.
+    pub(crate) fn assert_valid(&self) {
+        self.total_amount();
+        if !self.milestones.is_empty() {
+            assert_eq!(
+                self.milestones
+                    .iter()
+                    .try_fold(0, |sum: Balance, milestone| sum
+                        .checked_add(milestone.amount.0)),
+                Some(self.amount.0),
+                "ERR_BOUNTY_MILESTONES_AMOUNT"
+            );
+        }
+    }
+}
+
+/// Bounty information before milestones were introduced.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct BountyV1 {
+    pub description: String,
+    pub token: OldAccountId,
+    pub amount: U128,
+    pub times: u32,
+    pub max_deadline: U64,
+}
+
+impl From<BountyV1> for Bounty {
+    fn from(v1: BountyV1) -> Self {
+        Self {
+            description: v1.description,
+            token: v1.token,
+            amount: v1.amount,
+            times: v1.times,
+            max_deadline: v1.max_deadline,
+            milestones: vec![],
+            assignment: None,
+            paused: false,
+        }
+    }
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize)]
 #[cfg_attr(not(target_arch = "wasm32"), derive(Clone, Debug))]
 #[serde(crate = "near_sdk::serde")]
 pub enum VersionedBounty {
-    Default(Bounty),
+    Default(BountyV1),
+    Current(Bounty),
 }
 
 impl From<VersionedBounty> for Bounty {
     fn from(v: VersionedBounty) -> Self {
         match v {
-            VersionedBounty::Default(b) => b,
+            VersionedBounty::Default(b) => b.into(),
+            VersionedBounty::Current(b) => b,
         }
     }
 }
 
 impl Contract {
     /// Adds bounty to the storage and returns it's id.
-    /// Must not fail.
+    /// Funds for all the times the bounty can be done are reserved until it's paid out.
+    /// Fails if the treasury doesn't have enough of them available.
     pub(crate) fn internal_add_bounty(&mut self, bounty: &Bounty) -> u64 {
+        let total = bounty.total_amount();
+        assert!(
+            self.internal_available_amount(&bounty.token) >= total,
+            "ERR_NOT_ENOUGH_FUNDS"
+        );
         let id = self.last_bounty_id;
+        self.internal_reserve_bounty(id, &bounty.token, total);
         self.bounties
-            .insert(&id, &VersionedBounty::Default(bounty.clone()));
+            .insert(&id, &VersionedBounty::Current(bounty.clone()));
         self.last_bounty_id += 1;
+        Event::BountyAdded { bounty_id: id }.emit();
         id
     }
 
+    /// Reserves funds for the bounty, counted per bounty as well.
+    pub(crate) fn internal_reserve_bounty(
+        &mut self,
+        id: u64,
+        token_id: &OldAccountId,
+        amount: Balance,
+    ) {
+        let reserved = self.bounty_reserves.get(&id).unwrap_or_default() + amount;
+        self.bounty_reserves.insert(&id, &reserved);
+        self.internal_reserve(token_id, amount);
+    }
+
+    /// Releases funds of the bounty, at most what is still reserved for it.
+    /// The entry stays until the bounty is removed, so it's not reserved again by `reserve_bounties`.
+    pub(crate) fn internal_release_bounty(
+        &mut self,
+        id: u64,
+        token_id: &OldAccountId,
+        amount: Balance,
+    ) {
+        if let Some(reserved) = self.bounty_reserves.get(&id) {
+            let amount = amount.min(reserved);
+            self.bounty_reserves.insert(&id, &(reserved - amount));
+            self.internal_release(token_id, amount);
+        }
+    }
+
+    /// Applies the changes to the bounty, reserving or releasing the difference of its funds.
+    /// Bounties added before the upgrade are reserved in full by `reserve_bounties` instead.
+    /// The amount can't change while the bounty is claimed, as claims are paid the current amount.
+    pub(crate) fn internal_update_bounty(&mut self, id: u64, update: &BountyUpdate) {
+        let bounty: Bounty = self.bounties.get(&id).expect("ERR_NO_BOUNTY").into();
+        let updated = bounty.updated(update);
+        if updated.amount.0 != bounty.amount.0 {
+            assert!(
+                self.bounty_claims_count.get(&id).unwrap_or_default() == 0
+                    && self
+                        .bounty_partial_claims
+                        .get(&id)
+                        .unwrap_or_default()
+                        .is_empty(),
+                "ERR_BOUNTY_CLAIMED"
+            );
+        }
+        let (old_total, new_total) = (bounty.total_amount(), updated.total_amount());
+        if self.bounty_reserves.get(&id).is_some() {
+            if new_total > old_total {
+                assert!(
+                    self.internal_available_amount(&bounty.token) >= new_total - old_total,
+                    "ERR_NOT_ENOUGH_FUNDS"
+                );
+                self.internal_reserve_bounty(id, &bounty.token, new_total - old_total);
+            } else {
+                self.internal_release_bounty(id, &bounty.token, old_total - new_total);
+            }
+        }
+        self.bounties
+            .insert(&id, &VersionedBounty::Current(updated));
+        Event::BountyUpdated { bounty_id: id }.emit();
+    }
+
+    /// Removes the bounty and releases the funds still reserved for it.
+    /// Claims in progress are removed with their bonds returned, pending applications are rejected.
+    /// Fails while any claim is reported as done, the `BountyDone` proposal must be decided first,
+    /// or while claims made before the upgrade aren't indexed, see `index_bounty_claims`.
+    pub(crate) fn internal_remove_bounty(&mut self, policy: &Policy, id: u64) {
+        let bounty: Bounty = self.bounties.get(&id).expect("ERR_NO_BOUNTY").into();
+        let claimers = self.bounty_claims_index.get(&id).unwrap_or_default();
+        assert_eq!(
+            claimers.len() as u32,
+            self.bounty_claims_count.get(&id).unwrap_or_default(),
+            "ERR_BOUNTY_CLAIMS_NOT_INDEXED"
+        );
+        for account_id in claimers {
+            let (claims, claim_idx) = self.internal_get_claims(id, &account_id);
+            assert!(!claims[claim_idx].completed, "ERR_BOUNTY_DONE_PENDING");
+            self.internal_remove_claim(id, &account_id);
+            self.locked_amount -= policy.bounty_bond.0;
+            Promise::new(account_id.clone()).transfer(policy.bounty_bond.0);
+            Event::BountyClaimRemoved {
+                bounty_id: id,
+                account_id: &account_id,
+                bond_returned: true,
+            }
+            .emit();
+        }
+        for application in self.bounty_applications.get(&id).unwrap_or_default() {
+            self.internal_review_bounty_application(id, &application.account_id, false);
+        }
+        if let Some(reserved) = self.bounty_reserves.remove(&id) {
+            self.internal_release(&bounty.token, reserved);
+        }
+        self.bounties.remove(&id);
+        self.bounty_claims_count.remove(&id);
+        self.bounty_partial_claims.remove(&id);
+        Event::BountyRemoved { bounty_id: id }.emit();
+    }
+
     /// This must be called when proposal to payout bounty has been voted either successfully or not.
+    /// The claim is only updated once the payout succeeded, see `internal_bounty_paid`,
+    /// so a failed payout can be executed again. If rejected, the claim is removed.
     pub(crate) fn internal_execute_bounty_payout(
         &mut self,
         id: u64,
         receiver_id: &AccountId,
+        milestone: Option<u32>,
         success: bool,
     ) -> PromiseOrValue<()> {
         let bounty: Bounty = self.bounties.get(&id).expect("ERR_NO_BOUNTY").into();
-        self.internal_remove_claim(id, receiver_id);
         if success {
+            let memo = match milestone {
+                Some(milestone) => format!("Bounty {} milestone {} payout", id, milestone),
+                None => format!("Bounty {} payout", id),
+            };
             self.internal_payout(
                 &convert_old_to_new_token(&bounty.token),
                 receiver_id,
-                bounty.amount.0,
-                format!("Bounty {} payout", id),
+                bounty.payout_amount(milestone),
+                memo,
                 None,
             )
         } else {
+            self.internal_cancel_claim(id, receiver_id);
+            PromiseOrValue::Value(())
+        }
+    }
+
+    /// Records the succeeded payout of the `BountyDone` proposal and releases its funds.
+    /// For a milestone, the claim goes on until its last milestone is done.
+    /// Once the claim is over, the bounty is done one more time.
+    pub(crate) fn internal_bounty_paid(
+        &mut self,
+        id: u64,
+        receiver_id: &AccountId,
+        milestone: Option<u32>,
+    ) {
+        // Bounty can't be removed while the payout of its claim is pending.
+        let mut bounty: Bounty = self.bounties.get(&id).expect("ERR_NO_BOUNTY").into();
+        let amount = bounty.payout_amount(milestone);
+        self.internal_release_bounty(id, &bounty.token, amount);
+        self.internal_token_withdrawn(&bounty.token, amount);
+        if let Some(milestone) = milestone {
+            let key = (id, receiver_id.clone());
+            let mut done = self.bounty_claim_milestones.get(&key).unwrap_or_default();
+            done.push(milestone);
+            if done.len() < bounty.milestones.len() {
+                self.bounty_claim_milestones.insert(&key, &done);
+                let (mut claims, claim_idx) = self.internal_get_claims(id, receiver_id);
+                claims[claim_idx].completed = false;
+                self.bounty_claimers.insert(receiver_id, &claims);
+                return;
+            }
+        }
+        self.internal_remove_claim(id, receiver_id);
+        if bounty.times == 0 {
+            self.bounties.remove(&id);
+            self.bounty_reserves.remove(&id);
+            self.bounty_partial_claims.remove(&id);
+        } else {
+            bounty.times -= 1;
+            self.bounties.insert(&id, &VersionedBounty::Current(bounty));
+        }
+    }
+
+    /// Whether the claim reported by the `BountyDone` proposal is over after the proposal was executed,
+    /// i.e. it's not a milestone or it was the last milestone of the claim.
+    pub(crate) fn internal_bounty_claim_over(
+        &self,
+        id: u64,
+        receiver_id: &AccountId,
+        milestone: Option<u32>,
+    ) -> bool {
+        milestone.is_none()
+            || self
+                .bounty_claim_milestones
+                .get(&(id, receiver_id.clone()))
+                .is_none()
+    }
+
+    /// Adds the claim of `account_id` on the bounty, starting now.
+    fn internal_add_claim(&mut self, id: u64, account_id: &AccountId, deadline: U64) {
+        let bounty: Bounty = self.bounties.get(&id).expect("ERR_NO_BOUNTY").into();
+        let claims_count = self.bounty_claims_count.get(&id).unwrap_or_default();
+        assert!(claims_count < bounty.times, "ERR_BOUNTY_ALL_CLAIMED");
+        self.bounty_claims_count.insert(&id, &(claims_count + 1));
+        let mut accounts = self.bounty_claims_index.get(&id).unwrap_or_default();
+        accounts.push(account_id.clone());
+        self.bounty_claims_index.insert(&id, &accounts);
+        let mut claims = self.bounty_claimers.get(account_id).unwrap_or_default();
+        claims.push(BountyClaim {
+            bounty_id: id,
+            start_time: U64::from(env::block_timestamp()),
+            deadline,
+            completed: false,
+        });
+        self.bounty_claimers.insert(account_id, &claims);
+        // Claim takes over the milestones paid out to a claim that ended before it was done.
+        let mut partial_claims = self.bounty_partial_claims.get(&id).unwrap_or_default();
+        if let Some(done) = partial_claims.pop() {
+            self.bounty_claim_milestones
+                .insert(&(id, account_id.clone()), &done);
+            if partial_claims.is_empty() {
+                self.bounty_partial_claims.remove(&id);
+            } else {
+                self.bounty_partial_claims.insert(&id, &partial_claims);
+            }
+        }
+        Event::BountyClaimed {
+            bounty_id: id,
+            account_id,
+            deadline,
+        }
+        .emit();
+    }
+
+    /// Approves or rejects the application of `account_id` for the bounty.
+    /// Approved application becomes a claim starting now, rejected one gets its bond refunded.
+    /// Does nothing if there is no such application anymore.
+    pub(crate) fn internal_review_bounty_application(
+        &mut self,
+        id: u64,
+        account_id: &AccountId,
+        approve: bool,
+    ) -> PromiseOrValue<()> {
+        let mut applications = self.bounty_applications.get(&id).unwrap_or_default();
+        let application = match applications
+            .iter()
+            .position(|application| application.account_id == *account_id)
+        {
+            Some(idx) => applications.remove(idx),
+            None => return PromiseOrValue::Value(()),
+        };
+        if applications.is_empty() {
+            self.bounty_applications.remove(&id);
+        } else {
+            self.bounty_applications.insert(&id, &applications);
+        }
+        if approve {
+            // Bond of the application stays locked as the bond of the claim.
+            self.internal_add_claim(id, account_id, application.deadline);
             PromiseOrValue::Value(())
+        } else {
+            self.locked_amount -= application.bond.0;
+            Event::BountyApplicationRejected {
+                bounty_id: id,
+                account_id,
+            }
+            .emit();
+            Promise::new(account_id.clone())
+                .transfer(application.bond.0)
+                .into()
         }
     }
 
@@ -110,39 +504,121 @@ impl Contract {
             policy.bounty_bond.0,
             "ERR_BOUNTY_WRONG_BOND"
         );
-        let claims_count = self.bounty_claims_count.get(&id).unwrap_or_default();
-        assert!(claims_count < bounty.times, "ERR_BOUNTY_ALL_CLAIMED");
+        assert!(!bounty.paused, "ERR_BOUNTY_PAUSED");
         assert!(
             deadline.0 <= bounty.max_deadline.0,
             "ERR_BOUNTY_WRONG_DEADLINE"
         );
-        self.bounty_claims_count.insert(&id, &(claims_count + 1));
-        let mut claims = self
-            .bounty_claimers
-            .get(&env::predecessor_account_id())
-            .unwrap_or_default();
-        claims.push(BountyClaim {
-            bounty_id: id,
-            start_time: U64::from(env::block_timestamp()),
-            deadline,
-            completed: false,
-        });
-        self.bounty_claimers
-            .insert(&env::predecessor_account_id(), &claims);
+        let account_id = env::predecessor_account_id();
+        if bounty.assignment.is_some() {
+            let claims_count = self.bounty_claims_count.get(&id).unwrap_or_default();
+            assert!(claims_count < bounty.times, "ERR_BOUNTY_ALL_CLAIMED");
+            assert!(
+                self.bounty_claimers
+                    .get(&account_id)
+                    .and_then(|claims| self.internal_find_claim(id, &claims))
+                    .is_none(),
+                "ERR_BOUNTY_ALREADY_CLAIMED"
+            );
+            let mut applications = self.bounty_applications.get(&id).unwrap_or_default();
+            assert!(
+                applications
+                    .iter()
+                    .all(|application| application.account_id != account_id),
+                "ERR_BOUNTY_ALREADY_APPLIED"
+            );
+            assert!(
+                applications.len() < MAX_BOUNTY_APPLICATIONS,
+                "ERR_TOO_MANY_BOUNTY_APPLICATIONS"
+            );
+            applications.push(BountyApplication {
+                account_id: account_id.clone(),
+                deadline,
+                bond: U128(env::attached_deposit()),
+            });
+            self.bounty_applications.insert(&id, &applications);
+            Event::BountyApplied {
+                bounty_id: id,
+                account_id: &account_id,
+                deadline,
+            }
+            .emit();
+        } else {
+            self.internal_add_claim(id, &account_id, deadline);
+        }
         self.locked_amount += env::attached_deposit();
     }
 
+    /// Approves or rejects the application of `account_id` for the bounty.
+    /// Only members of the bounty's `reviewer_role` can call this.
+    pub fn bounty_review(
+        &mut self,
+        id: u64,
+        account_id: AccountId,
+        approve: bool,
+    ) -> PromiseOrValue<()> {
+        let bounty: Bounty = self.bounties.get(&id).expect("ERR_NO_BOUNTY").into();
+        let reviewer_role = bounty
+            .assignment
+            .and_then(|assignment| assignment.reviewer_role)
+            .expect("ERR_BOUNTY_NO_REVIEWER");
+        let policy = self.policy.get().unwrap().to_policy();
+        assert!(
+            policy.has_role(&self.internal_user_info(), &reviewer_role),
+            "ERR_NOT_BOUNTY_REVIEWER"
+        );
+        assert!(
+            self.bounty_applications
+                .get(&id)
+                .unwrap_or_default()
+                .iter()
+                .any(|application| application.account_id == account_id),
+            "ERR_NO_BOUNTY_APPLICATION"
+        );
+        self.internal_review_bounty_application(id, &account_id, approve)
+    }
+
+    /// Ends the claim of `claimer_id` without the bounty being done, e.g. expired or given up.
+    /// Milestones already paid out to it are kept for the next claim, which only does the remaining ones.
+    fn internal_cancel_claim(&mut self, bounty_id: u64, claimer_id: &AccountId) {
+        let done = self
+            .bounty_claim_milestones
+            .get(&(bounty_id, claimer_id.clone()))
+            .unwrap_or_default();
+        if !done.is_empty() && self.bounties.get(&bounty_id).is_some() {
+            let mut partial_claims = self
+                .bounty_partial_claims
+                .get(&bounty_id)
+                .unwrap_or_default();
+            partial_claims.push(done);
+            self.bounty_partial_claims
+                .insert(&bounty_id, &partial_claims);
+        }
+        self.internal_remove_claim(bounty_id, claimer_id);
+    }
+
     /// Remove the claim of `claimer_id` from this bounty.
     fn internal_remove_claim(&mut self, bounty_id: u64, claimer_id: &AccountId) {
         let (mut claims, claim_idx) = self.internal_get_claims(bounty_id, claimer_id);
         claims.remove(claim_idx);
+        self.bounty_claim_milestones
+            .remove(&(bounty_id, claimer_id.clone()));
         if claims.len() == 0 {
             self.bounty_claimers.remove(claimer_id);
         } else {
             self.bounty_claimers.insert(claimer_id, &claims);
         }
-        let count = self.bounty_claims_count.get(&bounty_id).unwrap() - 1;
-        self.bounty_claims_count.insert(&bounty_id, &count);
+        let mut accounts = self.bounty_claims_index.get(&bounty_id).unwrap_or_default();
+        accounts.retain(|account_id| account_id != claimer_id);
+        if accounts.is_empty() {
+            self.bounty_claims_index.remove(&bounty_id);
+        } else {
+            self.bounty_claims_index.insert(&bounty_id, &accounts);
+        }
+        // Count is gone if the bounty was removed.
+        if let Some(count) = self.bounty_claims_count.get(&bounty_id) {
+            self.bounty_claims_count.insert(&bounty_id, &(count - 1));
+        }
     }
 
     fn internal_get_claims(&mut self, id: u64, sender_id: &AccountId) -> (Vec<BountyClaim>, usize) {
@@ -159,14 +635,27 @@ impl Contract {
     /// Report that bounty is done. Creates a proposal to vote for paying out the bounty.
     /// Only creator of the claim can call `done` on bounty that is still in progress.
     /// On expired, anyone can call it to free up the claim slot.
+    /// Bounties with milestones are reported one `milestone` at a time, each paying out its share.
     #[payable]
-    pub fn bounty_done(&mut self, id: u64, account_id: Option<AccountId>, description: String) {
+    pub fn bounty_done(
+        &mut self,
+        id: u64,
+        account_id: Option<AccountId>,
+        description: String,
+        milestone: Option<u32>,
+    ) {
         let sender_id = account_id.unwrap_or_else(|| env::predecessor_account_id());
         let (mut claims, claim_idx) = self.internal_get_claims(id, &sender_id);
         assert!(!claims[claim_idx].completed, "ERR_BOUNTY_CLAIM_COMPLETED");
-        if env::block_timestamp() > claims[claim_idx].start_time.0 + claims[claim_idx].deadline.0 {
+        if env::block_timestamp() > claims[claim_idx].expires_at() {
             // Expired. Nothing to do.
-            self.internal_remove_claim(id, &sender_id);
+            self.internal_cancel_claim(id, &sender_id);
+            Event::BountyClaimRemoved {
+                bounty_id: id,
+                account_id: &sender_id,
+                bond_returned: false,
+            }
+            .emit();
         } else {
             // Still under deadline. Only the user themself can call this.
             assert_eq!(
@@ -174,15 +663,37 @@ impl Contract {
                 env::predecessor_account_id(),
                 "ERR_BOUNTY_DONE_MUST_BE_SELF"
             );
-            self.add_proposal(ProposalInput {
+            let bounty: Bounty = self.bounties.get(&id).expect("ERR_NO_BOUNTY").into();
+            match milestone {
+                Some(milestone) => {
+                    assert!(
+                        (milestone as usize) < bounty.milestones.len(),
+                        "ERR_BOUNTY_WRONG_MILESTONE"
+                    );
+                    let done = self
+                        .bounty_claim_milestones
+                        .get(&(id, sender_id.clone()))
+                        .unwrap_or_default();
+                    assert!(!done.contains(&milestone), "ERR_BOUNTY_MILESTONE_DONE");
+                }
+                None => assert!(bounty.milestones.is_empty(), "ERR_BOUNTY_NEEDS_MILESTONE"),
+            }
+            let proposal_id = self.add_proposal(ProposalInput {
                 description,
                 kind: ProposalKind::BountyDone {
                     bounty_id: id,
                     receiver_id: sender_id.clone(),
+                    milestone,
                 },
             });
             claims[claim_idx].completed = true;
             self.bounty_claimers.insert(&sender_id, &claims);
+            Event::BountyDone {
+                bounty_id: id,
+                account_id: &sender_id,
+                proposal_id,
+            }
+            .emit();
         }
     }
 
@@ -190,21 +701,67 @@ impl Contract {
     pub fn bounty_giveup(&mut self, id: u64) -> PromiseOrValue<()> {
         let policy = self.policy.get().unwrap().to_policy();
         let (claims, claim_idx) = self.internal_get_claims(id, &env::predecessor_account_id());
-        let result = if env::block_timestamp() - claims[claim_idx].start_time.0
-            > policy.bounty_forgiveness_period.0
-        {
-            // If user over the forgiveness period.
-            PromiseOrValue::Value(())
-        } else {
+        // Bond is returned in full if the bounty was removed meanwhile.
+        let bond_returned = env::block_timestamp() - claims[claim_idx].start_time.0
+            <= policy.bounty_forgiveness_period.0
+            || self.bounties.get(&id).is_none();
+        let result = if bond_returned {
             // Within forgiveness period. Return bond.
             self.locked_amount -= policy.bounty_bond.0;
             Promise::new(env::predecessor_account_id())
                 .transfer(policy.bounty_bond.0)
                 .into()
+        } else {
+            // If user over the forgiveness period.
+            PromiseOrValue::Value(())
         };
-        self.internal_remove_claim(id, &env::predecessor_account_id());
+        self.internal_cancel_claim(id, &env::predecessor_account_id());
+        Event::BountyClaimRemoved {
+            bounty_id: id,
+            account_id: &env::predecessor_account_id(),
+            bond_returned,
+        }
+        .emit();
         result
     }
+
+    /// Reserves the funds of bounties added before the upgrade, going through `limit` bounties from `from_index`.
+    /// Anyone can call this. Bounties already reserved are skipped, as are those without enough
+    /// available funds (e.g. the token isn't tracked yet), which can be reserved later.
+    pub fn reserve_bounties(&mut self, from_index: u64, limit: u64) {
+        let to_index = std::cmp::min(from_index.saturating_add(limit), self.last_bounty_id);
+        for id in from_index..to_index {
+            if self.bounty_reserves.get(&id).is_some() {
+                continue;
+            }
+            if let Some(bounty) = self.bounties.get(&id) {
+                let bounty: Bounty = bounty.into();
+                match bounty.amount.0.checked_mul(bounty.times as Balance) {
+                    Some(total) if self.internal_available_amount(&bounty.token) >= total => {
+                        self.internal_reserve_bounty(id, &bounty.token, total)
+                    }
+                    _ => log!("Bounty {} can't be reserved", id),
+                }
+            }
+        }
+    }
+
+    /// Adds the claims of given accounts that were made before the claims were indexed by bounty.
+    /// Anyone can call this, claims already indexed are skipped.
+    pub fn index_bounty_claims(&mut self, account_ids: Vec<AccountId>) {
+        for account_id in account_ids {
+            for claim in self.bounty_claimers.get(&account_id).unwrap_or_default() {
+                let mut accounts = self
+                    .bounty_claims_index
+                    .get(&claim.bounty_id)
+                    .unwrap_or_default();
+                if !accounts.contains(&account_id) {
+                    accounts.push(account_id.clone());
+                    self.bounty_claims_index.insert(&claim.bounty_id, &accounts);
+                }
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -213,7 +770,9 @@ mod tests {
     use near_sdk::testing_env;
     use near_sdk_sim::to_yocto;
 
+    use crate::proposals::ProposalStatus;
     use crate::proposals::{ProposalInput, ProposalKind};
+    use crate::test_utils::{execute, resolve_proposal};
     use crate::{Action, Config};
 
     use super::*;
@@ -229,6 +788,9 @@ mod tests {
                     amount: U128(to_yocto("10")),
                     times,
                     max_deadline: U64::from(1_000),
+                    milestones: vec![],
+                    assignment: None,
+                    paused: false,
                 },
             },
         });
@@ -250,6 +812,10 @@ mod tests {
 
         assert_eq!(contract.get_last_bounty_id(), 1);
         assert_eq!(contract.get_bounty(0).bounty.times, 2);
+        assert_eq!(
+            contract.internal_reserved_amount(OLD_BASE_TOKEN),
+            to_yocto("20")
+        );
 
         contract.bounty_claim(0, U64::from(500));
         assert_eq!(contract.get_bounty_claims(accounts(1)).len(), 1);
@@ -263,7 +829,7 @@ mod tests {
         assert_eq!(contract.get_bounty_claims(accounts(1)).len(), 1);
         assert_eq!(contract.get_bounty_number_of_claims(0), 1);
 
-        contract.bounty_done(0, None, "Bounty is done".to_string());
+        contract.bounty_done(0, None, "Bounty is done".to_string(), None);
         assert!(contract.get_bounty_claims(accounts(1))[0].completed);
 
         assert_eq!(contract.get_last_proposal_id(), 2);
@@ -284,9 +850,13 @@ mod tests {
 
         assert_eq!(contract.get_bounty_claims(accounts(1)).len(), 0);
         assert_eq!(contract.get_bounty(0).bounty.times, 1);
+        assert_eq!(
+            contract.internal_reserved_amount(OLD_BASE_TOKEN),
+            to_yocto("10")
+        );
 
         contract.bounty_claim(0, U64::from(500));
-        contract.bounty_done(0, None, "Bounty is done 2".to_string());
+        contract.bounty_done(0, None, "Bounty is done 2".to_string(), None);
         contract.act_proposal(2, Action::VoteApprove, None);
         testing_env!(
             context.build(),
@@ -298,6 +868,617 @@ mod tests {
         contract.on_proposal_callback(2);
 
         assert_eq!(contract.get_bounty(0).bounty.times, 0);
+        assert_eq!(contract.internal_reserved_amount(OLD_BASE_TOKEN), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ENOUGH_FUNDS")]
+    fn test_bounty_not_enough_funds() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .account_balance(to_yocto("30"))
+            .build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1).into()]),
+        );
+        add_bounty(&mut context, &mut contract, 2);
+        // Funds left after the first bounty can't cover another one.
+        add_bounty(&mut context, &mut contract, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_BOUNTY_AMOUNT_OVERFLOW")]
+    fn test_bounty_amount_overflow() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1).into()]),
+        );
+        testing_env!(context.attached_deposit(to_yocto("1")).build());
+        contract.add_proposal(ProposalInput {
+            description: "test".to_string(),
+            kind: ProposalKind::AddBounty {
+                bounty: BountyV1 {
+                    description: "test bounty".to_string(),
+                    token: String::from(OLD_BASE_TOKEN),
+                    amount: U128(Balance::MAX / 2),
+                    times: 3,
+                    max_deadline: U64::from(1_000),
+                }
+                .into(),
+            },
+        });
+    }
+
+    #[test]
+    fn test_reserve_legacy_bounties() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1).into()]),
+        );
+        // Bounties added before the upgrade, the second one in a token that isn't tracked.
+        for token in [String::from(OLD_BASE_TOKEN), accounts(3).to_string()] {
+            contract.bounties.insert(
+                &contract.last_bounty_id,
+                &VersionedBounty::Default(BountyV1 {
+                    description: "test bounty".to_string(),
+                    token,
+                    amount: U128(to_yocto("10")),
+                    times: 2,
+                    max_deadline: U64::from(1_000),
+                }),
+            );
+            contract.last_bounty_id += 1;
+        }
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.reserve_bounties(0, 10);
+        contract.reserve_bounties(0, 10);
+        assert_eq!(
+            contract.internal_reserved_amount(OLD_BASE_TOKEN),
+            to_yocto("20")
+        );
+        assert_eq!(contract.internal_reserved_amount(accounts(3).as_str()), 0);
+        assert!(contract.bounty_reserves.get(&1).is_none());
+
+        // Payouts of a bounty that isn't reserved don't release the funds of others.
+        contract.internal_release_bounty(1, &accounts(3).to_string(), to_yocto("10"));
+        contract.internal_release_bounty(0, &String::from(OLD_BASE_TOKEN), to_yocto("10"));
+        assert_eq!(
+            contract.internal_reserved_amount(OLD_BASE_TOKEN),
+            to_yocto("10")
+        );
+    }
+
+    /// Approves the `BountyDone` proposal and resolves its payout.
+    fn approve_done(context: &mut VMContextBuilder, contract: &mut Contract, id: u64) {
+        contract.act_proposal(id, Action::VoteApprove, None);
+        resolve_proposal(context, contract, id, PromiseResult::Successful(vec![]));
+    }
+
+    /// Adds a bounty that is done once, in milestones of 4 and 6 NEAR.
+    fn add_milestone_bounty(context: &mut VMContextBuilder, contract: &mut Contract) {
+        testing_env!(context.attached_deposit(to_yocto("1")).build());
+        let milestone = |amount| BountyMilestone {
+            description: "part".to_string(),
+            amount: U128(to_yocto(amount)),
+        };
+        let id = contract.add_proposal(ProposalInput {
+            description: "test".to_string(),
+            kind: ProposalKind::AddBounty {
+                bounty: Bounty {
+                    description: "test bounty".to_string(),
+                    token: String::from(OLD_BASE_TOKEN),
+                    amount: U128(to_yocto("10")),
+                    times: 1,
+                    max_deadline: U64::from(1_000),
+                    milestones: vec![milestone("4"), milestone("6")],
+                    assignment: None,
+                    paused: false,
+                },
+            },
+        });
+        contract.act_proposal(id, Action::VoteApprove, None);
+    }
+
+    #[test]
+    fn test_bounty_milestones() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1).into()]),
+        );
+        add_milestone_bounty(&mut context, &mut contract);
+
+        contract.bounty_claim(0, U64::from(500));
+        contract.bounty_done(0, None, "Second part".to_string(), Some(1));
+        approve_done(&mut context, &mut contract, 1);
+        // Claim goes on with the other milestone.
+        let claims = contract.get_bounty_claims(accounts(1));
+        assert_eq!(claims.len(), 1);
+        assert!(!claims[0].completed);
+        assert_eq!(
+            contract.get_bounty_claim_milestones(0, accounts(1)),
+            vec![1]
+        );
+        assert_eq!(contract.get_bounty(0).bounty.times, 1);
+
+        contract.bounty_done(0, None, "First part".to_string(), Some(0));
+        approve_done(&mut context, &mut contract, 2);
+        assert_eq!(contract.get_bounty_claims(accounts(1)).len(), 0);
+        assert!(contract
+            .get_bounty_claim_milestones(0, accounts(1))
+            .is_empty());
+        assert_eq!(contract.get_bounty(0).bounty.times, 0);
+    }
+
+    #[test]
+    fn test_bounty_milestone_payout_failed() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1).into()]),
+        );
+        add_milestone_bounty(&mut context, &mut contract);
+        contract.bounty_claim(0, U64::from(500));
+        contract.bounty_done(0, None, "First part".to_string(), Some(0));
+        contract.act_proposal(1, Action::VoteApprove, None);
+        resolve_proposal(&context, &mut contract, 1, PromiseResult::Failed);
+
+        // Milestone is not done until its payout succeeds.
+        assert_eq!(
+            contract.get_proposal(1).proposal.status,
+            ProposalStatus::Failed
+        );
+        assert!(contract
+            .get_bounty_claim_milestones(0, accounts(1))
+            .is_empty());
+        assert!(contract.get_bounty_claims(accounts(1))[0].completed);
+        assert_eq!(
+            contract.internal_reserved_amount(OLD_BASE_TOKEN),
+            to_yocto("10")
+        );
+
+        // Failed payout is executed again.
+        contract.act_proposal(1, Action::Finalize, None);
+        resolve_proposal(
+            &context,
+            &mut contract,
+            1,
+            PromiseResult::Successful(vec![]),
+        );
+        assert_eq!(
+            contract.get_bounty_claim_milestones(0, accounts(1)),
+            vec![0]
+        );
+        assert!(!contract.get_bounty_claims(accounts(1))[0].completed);
+        assert_eq!(
+            contract.internal_reserved_amount(OLD_BASE_TOKEN),
+            to_yocto("6")
+        );
+    }
+
+    /// Milestones paid out to a claim that expired are taken over by the next claim.
+    #[test]
+    fn test_bounty_milestones_claim_expired() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1).into()]),
+        );
+        add_milestone_bounty(&mut context, &mut contract);
+
+        contract.bounty_claim(0, U64::from(500));
+        contract.bounty_done(0, None, "First part".to_string(), Some(0));
+        approve_done(&mut context, &mut contract, 1);
+        assert_eq!(
+            contract.internal_reserved_amount(OLD_BASE_TOKEN),
+            to_yocto("6")
+        );
+
+        // Anyone can remove the expired claim.
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(501)
+            .build());
+        contract.bounty_done(0, Some(accounts(1)), String::new(), None);
+        assert_eq!(
+            contract.internal_reserved_amount(OLD_BASE_TOKEN),
+            to_yocto("6")
+        );
+
+        // New claimer only does the remaining milestone.
+        contract.bounty_claim(0, U64::from(500));
+        assert_eq!(
+            contract.get_bounty_claim_milestones(0, accounts(2)),
+            vec![0]
+        );
+        contract.bounty_done(0, None, "Second part".to_string(), Some(1));
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        approve_done(&mut context, &mut contract, 2);
+        assert!(contract.get_bounty_claims(accounts(2)).is_empty());
+        assert_eq!(contract.get_bounty(0).bounty.times, 0);
+        assert_eq!(contract.internal_reserved_amount(OLD_BASE_TOKEN), 0);
+    }
+
+    #[test]
+    fn test_bounty_assignment() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1).into()]),
+        );
+        testing_env!(context.attached_deposit(to_yocto("1")).build());
+        let id = contract.add_proposal(ProposalInput {
+            description: "test".to_string(),
+            kind: ProposalKind::AddBounty {
+                bounty: Bounty {
+                    description: "test bounty".to_string(),
+                    token: String::from(OLD_BASE_TOKEN),
+                    amount: U128(to_yocto("10")),
+                    times: 1,
+                    max_deadline: U64::from(1_000),
+                    milestones: vec![],
+                    assignment: Some(BountyAssignment {
+                        reviewer_role: Some("council".to_string()),
+                    }),
+                    paused: false,
+                },
+            },
+        });
+        contract.act_proposal(id, Action::VoteApprove, None);
+
+        for applicant in [accounts(2), accounts(3)] {
+            testing_env!(context.predecessor_account_id(applicant).build());
+            contract.bounty_claim(0, U64::from(500));
+        }
+        assert_eq!(contract.get_bounty_applications(0).len(), 2);
+        assert_eq!(contract.get_bounty_number_of_claims(0), 0);
+        let locked = contract.locked_amount;
+
+        // Reviewer rejects one applicant, refunding the bond.
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(0)
+            .build());
+        contract.bounty_review(0, accounts(3), false);
+        assert_eq!(contract.locked_amount, locked - to_yocto("1"));
+
+        // The other one is approved by a proposal, the claim starts then.
+        context.block_timestamp(100);
+        testing_env!(context.attached_deposit(to_yocto("1")).build());
+        let id = contract.add_proposal(ProposalInput {
+            description: "assign".to_string(),
+            kind: ProposalKind::ApproveBountyClaim {
+                bounty_id: 0,
+                account_id: accounts(2),
+            },
+        });
+        contract.act_proposal(id, Action::VoteApprove, None);
+        assert!(contract.get_bounty_applications(0).is_empty());
+        assert_eq!(contract.get_bounty_number_of_claims(0), 1);
+        let claims = contract.get_bounty_claims(accounts(2));
+        assert_eq!(claims[0].start_time, U64(100));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_TOO_MANY_BOUNTY_APPLICATIONS")]
+    fn test_bounty_too_many_applications() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1).into()]),
+        );
+        testing_env!(context.attached_deposit(to_yocto("1")).build());
+        let id = contract.add_proposal(ProposalInput {
+            description: "test".to_string(),
+            kind: ProposalKind::AddBounty {
+                bounty: Bounty {
+                    description: "test bounty".to_string(),
+                    token: String::from(OLD_BASE_TOKEN),
+                    amount: U128(to_yocto("10")),
+                    times: 1,
+                    max_deadline: U64::from(1_000),
+                    milestones: vec![],
+                    assignment: Some(BountyAssignment {
+                        reviewer_role: None,
+                    }),
+                    paused: false,
+                },
+            },
+        });
+        contract.act_proposal(id, Action::VoteApprove, None);
+
+        for i in 0..=MAX_BOUNTY_APPLICATIONS {
+            let applicant: AccountId = format!("applicant{}.near", i).parse().unwrap();
+            testing_env!(context.predecessor_account_id(applicant).build());
+            contract.bounty_claim(0, U64::from(500));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_BOUNTY_REVIEWER")]
+    fn test_bounty_review_not_reviewer() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1).into()]),
+        );
+        let mut bounty: Bounty = BountyV1 {
+            description: "test bounty".to_string(),
+            token: String::from(OLD_BASE_TOKEN),
+            amount: U128(to_yocto("10")),
+            times: 1,
+            max_deadline: U64::from(1_000),
+        }
+        .into();
+        bounty.assignment = Some(BountyAssignment {
+            reviewer_role: Some("council".to_string()),
+        });
+        contract.internal_add_bounty(&bounty);
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(to_yocto("1"))
+            .build());
+        contract.bounty_claim(0, U64::from(500));
+        testing_env!(context.attached_deposit(0).build());
+        contract.bounty_review(0, accounts(2), true);
+    }
+
+    #[test]
+    fn test_update_and_remove_bounty() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1).into()]),
+        );
+        add_bounty(&mut context, &mut contract, 2);
+        execute(
+            &mut context,
+            &mut contract,
+            ProposalKind::UpdateBounty {
+                bounty_id: 0,
+                update: BountyUpdate {
+                    amount: Some(U128(to_yocto("5"))),
+                    max_deadline: Some(U64::from(2_000)),
+                    paused: None,
+                },
+            },
+        );
+        let bounty = contract.get_bounty(0).bounty;
+        assert_eq!(bounty.amount, U128(to_yocto("5")));
+        assert_eq!(bounty.max_deadline, U64::from(2_000));
+        assert_eq!(
+            contract.internal_reserved_amount(OLD_BASE_TOKEN),
+            to_yocto("10")
+        );
+
+        // Once claimed, the bounty can still be paused.
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(to_yocto("1"))
+            .build());
+        contract.bounty_claim(0, U64::from(500));
+        execute(
+            &mut context,
+            &mut contract,
+            ProposalKind::UpdateBounty {
+                bounty_id: 0,
+                update: BountyUpdate {
+                    amount: None,
+                    max_deadline: None,
+                    paused: Some(true),
+                },
+            },
+        );
+        assert!(contract.get_bounty(0).bounty.paused);
+
+        // Claim in progress is removed with its bond returned.
+        let locked = contract.locked_amount;
+        execute(
+            &mut context,
+            &mut contract,
+            ProposalKind::RemoveBounty { bounty_id: 0 },
+        );
+        assert!(contract.get_bounties(0, 10).is_empty());
+        assert!(contract.get_bounty_claims(accounts(2)).is_empty());
+        assert_eq!(contract.locked_amount, locked - to_yocto("1"));
+        assert_eq!(contract.internal_reserved_amount(OLD_BASE_TOKEN), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_BOUNTY_CLAIMED")]
+    fn test_update_claimed_bounty_amount() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1).into()]),
+        );
+        add_bounty(&mut context, &mut contract, 2);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.bounty_claim(0, U64::from(500));
+        execute(
+            &mut context,
+            &mut contract,
+            ProposalKind::UpdateBounty {
+                bounty_id: 0,
+                update: BountyUpdate {
+                    amount: Some(U128(to_yocto("5"))),
+                    max_deadline: None,
+                    paused: None,
+                },
+            },
+        );
+    }
+
+    #[test]
+    fn test_remove_bounty_milestone_paid() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1).into()]),
+        );
+        add_milestone_bounty(&mut context, &mut contract);
+        contract.bounty_claim(0, U64::from(500));
+        contract.bounty_done(0, None, "First part".to_string(), Some(0));
+        approve_done(&mut context, &mut contract, 1);
+
+        // Only the milestone that wasn't paid out is released.
+        let locked = contract.locked_amount;
+        execute(
+            &mut context,
+            &mut contract,
+            ProposalKind::RemoveBounty { bounty_id: 0 },
+        );
+        assert!(contract.get_bounty_claims(accounts(1)).is_empty());
+        assert_eq!(contract.locked_amount, locked - to_yocto("1"));
+        assert_eq!(contract.internal_reserved_amount(OLD_BASE_TOKEN), 0);
+        assert!(contract.bounty_reserves.get(&0).is_none());
+    }
+
+    #[test]
+    fn test_remove_bounty_legacy_claim() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1).into()]),
+        );
+        add_bounty(&mut context, &mut contract, 2);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.bounty_claim(0, U64::from(500));
+        contract.bounty_claims_index.remove(&0);
+
+        // Claimer made before the upgrade gets the bond back once indexed.
+        contract.index_bounty_claims(vec![accounts(2)]);
+        let locked = contract.locked_amount;
+        execute(
+            &mut context,
+            &mut contract,
+            ProposalKind::RemoveBounty { bounty_id: 0 },
+        );
+        assert!(contract.get_bounty_claims(accounts(2)).is_empty());
+        assert_eq!(contract.locked_amount, locked - to_yocto("1"));
+        assert_eq!(contract.internal_reserved_amount(OLD_BASE_TOKEN), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_BOUNTY_CLAIMS_NOT_INDEXED")]
+    fn test_remove_bounty_claim_not_indexed() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1).into()]),
+        );
+        add_bounty(&mut context, &mut contract, 2);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.bounty_claim(0, U64::from(500));
+        contract.bounty_claims_index.remove(&0);
+        execute(
+            &mut context,
+            &mut contract,
+            ProposalKind::RemoveBounty { bounty_id: 0 },
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_BOUNTY_PAUSED")]
+    fn test_bounty_claim_paused() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1).into()]),
+        );
+        add_bounty(&mut context, &mut contract, 1);
+        execute(
+            &mut context,
+            &mut contract,
+            ProposalKind::UpdateBounty {
+                bounty_id: 0,
+                update: BountyUpdate {
+                    amount: None,
+                    max_deadline: None,
+                    paused: Some(true),
+                },
+            },
+        );
+        testing_env!(context.attached_deposit(to_yocto("1")).build());
+        contract.bounty_claim(0, U64::from(500));
+    }
+
+    #[test]
+    fn test_bounty_claims_by_bounty() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1).into()]),
+        );
+        add_bounty(&mut context, &mut contract, 2);
+        contract.bounty_claim(0, U64::from(500));
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.bounty_claim(0, U64::from(1_000));
+
+        context.block_timestamp(600);
+        testing_env!(context.build());
+        let claims = contract.get_bounty_claims_by_bounty(0, 0, 10);
+        assert_eq!(
+            claims
+                .iter()
+                .map(|claim| (claim.account_id.clone(), claim.expired))
+                .collect::<Vec<_>>(),
+            vec![(accounts(1), true), (accounts(2), false)]
+        );
+        assert_eq!(claims[1].expires_at, U64(1_000));
+        assert_eq!(claims[1].claim.start_time, U64(0));
+
+        // Expired claim is freed up, the index follows.
+        contract.bounty_done(0, Some(accounts(1)), "expired".to_string(), None);
+        let claims = contract.get_bounty_claims_by_bounty(0, 0, 10);
+        assert_eq!(claims.len(), 1);
+        assert_eq!(claims[0].account_id, accounts(2));
+        assert!(contract.get_bounty_claims_by_bounty(0, 1, 10).is_empty());
+    }
+
+    #[test]
+    fn test_index_legacy_bounty_claims() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1).into()]),
+        );
+        add_bounty(&mut context, &mut contract, 2);
+        contract.bounty_claim(0, U64::from(500));
+        // Claim made before the upgrade is not in the index.
+        contract.bounty_claims_index.remove(&0);
+        assert!(contract.get_bounty_claims_by_bounty(0, 0, 10).is_empty());
+
+        contract.index_bounty_claims(vec![accounts(1), accounts(2)]);
+        contract.index_bounty_claims(vec![accounts(1)]);
+        let claims = contract.get_bounty_claims_by_bounty(0, 0, 10);
+        assert_eq!(claims.len(), 1);
+        assert_eq!(claims[0].account_id, accounts(1));
+
+        // Indexed claim is removed from the index as well.
+        contract.bounty_giveup(0);
+        assert!(contract.get_bounty_claims_by_bounty(0, 0, 10).is_empty());
     }
 
     #[test]
@@ -311,7 +1492,7 @@ mod tests {
         );
         let id = add_bounty(&mut context, &mut contract, 1);
         contract.bounty_claim(id, U64::from(500));
-        contract.bounty_done(id, None, "Bounty is done 2".to_string());
+        contract.bounty_done(id, None, "Bounty is done 2".to_string(), None);
         contract.bounty_claim(id, U64::from(500));
     }
 }
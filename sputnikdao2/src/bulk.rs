@@ -0,0 +1,287 @@
+//! Bulk transfers: payouts listed in a blob, executed in batches that fit into the attached gas.
+//! Rows are copied to the state once the proposal is approved and each keeps its own status,
+//! so failed rows can be retried without paying the others again.
+
+use std::collections::HashMap;
+
+use near_sdk::serde_json;
+use near_sdk::PromiseOrValue;
+
+use crate::events::Event;
+use crate::types::{
+    convert_old_to_new_token, GAS_FOR_BULK_TRANSFER_CALLBACK, GAS_FOR_BULK_TRANSFER_ROW,
+};
+use crate::*;
+
+/// Max number of rows in a bulk transfer.
+pub const MAX_BULK_TRANSFER_ROWS: usize = 500;
+
+/// Payout listed in the blob of a bulk transfer.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct Payout {
+    pub receiver_id: AccountId,
+    /// Can be "" for $NEAR or a valid account id.
+    pub token_id: OldAccountId,
+    pub amount: U128,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub enum BulkTransferStatus {
+    /// Waiting for the next batch.
+    Pending,
+    /// Payout was sent, waiting for its result.
+    Sent,
+    Succeeded,
+    /// Payout failed, can be retried with `bulk_transfer_retry`.
+    Failed,
+}
+
+/// Row of an approved bulk transfer.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct BulkTransferRow {
+    #[serde(flatten)]
+    pub payout: Payout,
+    pub status: BulkTransferStatus,
+}
+
+/// Reads and validates the payouts stored in the blob with given hash.
+pub(crate) fn read_bulk_transfer(hash: &Base58CryptoHash) -> Vec<Payout> {
+    let blob = env::storage_read(&CryptoHash::from(*hash)).expect("ERR_NO_BLOB");
+    let payouts: Vec<Payout> = serde_json::from_slice(&blob).expect("ERR_INVALID_BULK_TRANSFER");
+    assert!(
+        !payouts.is_empty() && payouts.len() <= MAX_BULK_TRANSFER_ROWS,
+        "ERR_INVALID_BULK_TRANSFER"
+    );
+    assert!(
+        payouts.iter().all(|payout| payout.amount.0 > 0),
+        "ERR_ZERO_AMOUNT"
+    );
+    payouts
+}
+
+impl Contract {
+    /// Stores the rows of the approved bulk transfer, reserves the totals per token and sends the first batch.
+    /// Fails if the totals per token would spend funds that are reserved.
+    pub(crate) fn internal_start_bulk_transfer(
+        &mut self,
+        proposal_id: u64,
+        hash: &Base58CryptoHash,
+    ) {
+        let payouts = read_bulk_transfer(hash);
+        let mut totals: HashMap<&OldAccountId, Balance> = HashMap::new();
+        for payout in payouts.iter() {
+            *totals.entry(&payout.token_id).or_default() += payout.amount.0;
+        }
+        for (token_id, total) in totals {
+            self.assert_spendable(token_id, total);
+            self.internal_reserve(token_id, total);
+        }
+        let rows = payouts
+            .into_iter()
+            .map(|payout| BulkTransferRow {
+                payout,
+                status: BulkTransferStatus::Pending,
+            })
+            .collect();
+        self.bulk_transfers.insert(&proposal_id, &rows);
+        self.internal_bulk_transfer_batch(proposal_id);
+    }
+
+    /// Sends as many pending rows of the bulk transfer as the remaining gas allows.
+    /// Returns number of sent rows.
+    fn internal_bulk_transfer_batch(&mut self, proposal_id: u64) -> u32 {
+        let mut rows = self
+            .bulk_transfers
+            .get(&proposal_id)
+            .expect("ERR_NO_BULK_TRANSFER");
+        let max_rows = (env::prepaid_gas().0 - env::used_gas().0) / GAS_FOR_BULK_TRANSFER_ROW.0;
+        let mut sent = 0;
+        for (row, item) in rows.iter_mut().enumerate() {
+            if sent as u64 == max_rows {
+                break;
+            }
+            if item.status != BulkTransferStatus::Pending {
+                continue;
+            }
+            let payout = match self.internal_payout(
+                &convert_old_to_new_token(&item.payout.token_id),
+                &item.payout.receiver_id,
+                item.payout.amount.0,
+                format!("Bulk transfer {}", proposal_id),
+                None,
+            ) {
+                PromiseOrValue::Promise(promise) => promise,
+                PromiseOrValue::Value(()) => unreachable!(),
+            };
+            payout.then(ext_self::on_bulk_transfer_callback(
+                proposal_id,
+                row as u32,
+                env::current_account_id(),
+                0,
+                GAS_FOR_BULK_TRANSFER_CALLBACK,
+            ));
+            item.status = BulkTransferStatus::Sent;
+            sent += 1;
+        }
+        self.bulk_transfers.insert(&proposal_id, &rows);
+        sent
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Sends the next batch of pending rows of the bulk transfer. Anyone can call this.
+    /// Returns number of sent rows, attach more gas to send more at once.
+    pub fn bulk_transfer_continue(&mut self, proposal_id: u64) -> u32 {
+        self.internal_bulk_transfer_batch(proposal_id)
+    }
+
+    /// Marks failed rows of the bulk transfer as pending again and sends the next batch.
+    /// Anyone can call this, e.g. after the receivers fixed what made their payouts fail.
+    pub fn bulk_transfer_retry(&mut self, proposal_id: u64) -> u32 {
+        let mut rows = self
+            .bulk_transfers
+            .get(&proposal_id)
+            .expect("ERR_NO_BULK_TRANSFER");
+        for item in rows.iter_mut() {
+            if item.status == BulkTransferStatus::Failed {
+                item.status = BulkTransferStatus::Pending;
+            }
+        }
+        self.bulk_transfers.insert(&proposal_id, &rows);
+        self.internal_bulk_transfer_batch(proposal_id)
+    }
+
+    /// Receiving callback after the payout of the bulk transfer row.
+    #[private]
+    pub fn on_bulk_transfer_callback(&mut self, proposal_id: u64, row: u32) {
+        assert_eq!(
+            env::promise_results_count(),
+            1,
+            "ERR_UNEXPECTED_CALLBACK_PROMISES"
+        );
+        let mut rows = self
+            .bulk_transfers
+            .get(&proposal_id)
+            .expect("ERR_NO_BULK_TRANSFER");
+        let item = &mut rows[row as usize];
+        let success = matches!(env::promise_result(0), PromiseResult::Successful(_));
+        if success {
+            item.status = BulkTransferStatus::Succeeded;
+            let payout = item.payout.clone();
+            self.internal_release(&payout.token_id, payout.amount.0);
+            self.internal_token_withdrawn(&payout.token_id, payout.amount.0);
+        } else {
+            item.status = BulkTransferStatus::Failed;
+        }
+        self.bulk_transfers.insert(&proposal_id, &rows);
+        Event::BulkTransferRowDone {
+            proposal_id,
+            row,
+            success,
+        }
+        .emit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, Gas, PromiseResult};
+    use near_sdk_sim::to_yocto;
+
+    use crate::test_utils::promise_result;
+
+    use super::*;
+
+    fn callback(context: &mut VMContextBuilder, contract: &mut Contract, row: u32, success: bool) {
+        promise_result(
+            context,
+            if success {
+                PromiseResult::Successful(vec![])
+            } else {
+                PromiseResult::Failed
+            },
+        );
+        contract.on_bulk_transfer_callback(0, row);
+    }
+
+    fn statuses(contract: &Contract) -> Vec<BulkTransferStatus> {
+        contract
+            .get_bulk_transfer(0, 0, 10)
+            .into_iter()
+            .map(|row| row.status)
+            .collect()
+    }
+
+    #[test]
+    fn test_bulk_transfer() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context
+            .current_account_id(accounts(0))
+            .predecessor_account_id(accounts(1))
+            .account_balance(to_yocto("1000"))
+            .build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1)]),
+        );
+        let payouts: Vec<Payout> = (2..5)
+            .map(|i| Payout {
+                receiver_id: accounts(i),
+                token_id: String::from(OLD_BASE_TOKEN),
+                amount: U128(to_yocto("1")),
+            })
+            .collect();
+        let blob = serde_json::to_vec(&payouts).unwrap();
+        let hash = env::sha256(&blob);
+        env::storage_write(&hash, &blob);
+        let mut hash_bytes = [0u8; 32];
+        hash_bytes.copy_from_slice(&hash);
+
+        testing_env!(context.attached_deposit(to_yocto("1")).build());
+        contract.add_proposal(ProposalInput {
+            description: "monthly payouts".to_string(),
+            kind: ProposalKind::BulkTransfer {
+                hash: Base58CryptoHash::from(hash_bytes),
+            },
+        });
+        // Gas is enough for two rows only.
+        testing_env!(context
+            .attached_deposit(0)
+            .prepaid_gas(Gas(GAS_FOR_BULK_TRANSFER_ROW.0 * 5 / 2))
+            .build());
+        contract.act_proposal(0, Action::VoteApprove, None);
+        use BulkTransferStatus::*;
+        assert_eq!(statuses(&contract), vec![Sent, Sent, Pending]);
+
+        assert_eq!(
+            contract.internal_reserved_amount(OLD_BASE_TOKEN),
+            to_yocto("3")
+        );
+
+        // Succeeded rows release their amount, failed ones keep it reserved for the retry.
+        callback(&mut context, &mut contract, 0, true);
+        callback(&mut context, &mut contract, 1, false);
+        assert_eq!(statuses(&contract), vec![Succeeded, Failed, Pending]);
+        assert_eq!(
+            contract.internal_reserved_amount(OLD_BASE_TOKEN),
+            to_yocto("2")
+        );
+
+        // Continuing sends only pending rows, retrying sends the failed ones again.
+        assert_eq!(contract.bulk_transfer_continue(0), 1);
+        callback(&mut context, &mut contract, 2, true);
+        assert_eq!(contract.bulk_transfer_continue(0), 0);
+        assert_eq!(contract.bulk_transfer_retry(0), 1);
+        assert_eq!(statuses(&contract), vec![Succeeded, Sent, Succeeded]);
+        callback(&mut context, &mut contract, 1, true);
+        assert_eq!(contract.internal_reserved_amount(OLD_BASE_TOKEN), 0);
+    }
+}
@@ -0,0 +1,361 @@
+//! Conviction voting for continuous funding.
+//! Members stake their delegated weight behind `ConvictionFunding` proposals. Conviction of the
+//! proposal moves towards the staked amount every period, and the proposal passes once conviction
+//! crosses a threshold that grows with the requested share of the treasury.
+
+use std::collections::HashMap;
+
+use near_sdk::json_types::U64;
+
+use crate::events::Event;
+use crate::types::convert_old_to_new_token;
+use crate::*;
+
+/// Fixed point precision of ratios in conviction math.
+const PRECISION: u128 = 1_000_000_000;
+
+/// Maximum number of proposals an account can stake on at once.
+/// Bounds the gas of releasing the stakes on undelegation.
+pub const MAX_CONVICTION_STAKES: usize = 10;
+
+/// Parameters of conviction voting, shared by all `ConvictionFunding` proposals.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct ConvictionPolicy {
+    /// Period in which conviction decays and accrues.
+    pub period: U64,
+    /// Share of conviction left after each period, as `[numerator, denominator]`.
+    /// The rest of it is replaced by the currently staked amount.
+    pub decay: (u64, u64),
+    /// Share of the treasury from which requests can't pass.
+    pub max_ratio: (u64, u64),
+    /// Scales the threshold: request of share `s` of the treasury needs conviction of
+    /// `weight / (max_ratio - s)^2` of the total delegated amount.
+    pub weight: (u64, u64),
+}
+
+impl Default for ConvictionPolicy {
+    fn default() -> Self {
+        Self {
+            period: U64::from(1_000_000_000 * 60 * 60 * 24),
+            decay: (9, 10),
+            max_ratio: (1, 5),
+            weight: (1, 400),
+        }
+    }
+}
+
+impl ConvictionPolicy {
+    /// Conviction needed to pass a request of `amount` from `treasury`, when `total_amount` is delegated.
+    /// Returns None if the request is too large to ever pass.
+    pub fn threshold(
+        &self,
+        amount: Balance,
+        treasury: Balance,
+        total_amount: Balance,
+    ) -> Option<Balance> {
+        if amount >= treasury || total_amount == 0 {
+            return None;
+        }
+        let share = ratio_of(amount, treasury);
+        let max_ratio = to_fixed(self.max_ratio);
+        if share >= max_ratio {
+            return None;
+        }
+        let gap = max_ratio - share;
+        let fraction = to_fixed(self.weight) * PRECISION / gap * PRECISION / gap;
+        if fraction > PRECISION {
+            return None;
+        }
+        Some(std::cmp::max(mul_fixed(total_amount, fraction), 1))
+    }
+}
+
+/// Staked weight and accrued conviction of a `ConvictionFunding` proposal.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct Conviction {
+    /// Weight currently staked behind the proposal.
+    pub staked: U128,
+    /// Conviction as of `updated_at`.
+    pub amount: U128,
+    /// Start of the period conviction was last accrued in.
+    pub updated_at: U64,
+}
+
+impl Conviction {
+    pub fn new(now: u64) -> Self {
+        Self {
+            staked: U128(0),
+            amount: U128(0),
+            updated_at: U64(now),
+        }
+    }
+
+    /// Returns conviction as of `now` and the start of the period `now` falls in.
+    fn accrued(&self, policy: &ConvictionPolicy, now: u64) -> (Balance, u64) {
+        let period = policy.period.0;
+        if period == 0 {
+            return (self.staked.0, now);
+        }
+        let periods = now.saturating_sub(self.updated_at.0) / period;
+        let remaining = pow_fixed(to_fixed(policy.decay), periods);
+        (
+            mul_fixed(self.amount.0, remaining) + mul_fixed(self.staked.0, PRECISION - remaining),
+            self.updated_at.0 + periods * period,
+        )
+    }
+
+    /// Conviction as of `now`.
+    pub fn current(&self, policy: &ConvictionPolicy, now: u64) -> Balance {
+        self.accrued(policy, now).0
+    }
+
+    /// Accrues conviction with the old stake up to `now` and adds `amount` to the stake.
+    pub fn stake(&mut self, policy: &ConvictionPolicy, now: u64, amount: Balance) {
+        self.accrue(policy, now);
+        self.staked = U128(self.staked.0 + amount);
+    }
+
+    /// Accrues conviction with the old stake up to `now` and takes `amount` from the stake.
+    pub fn unstake(&mut self, policy: &ConvictionPolicy, now: u64, amount: Balance) {
+        self.accrue(policy, now);
+        self.staked = U128(self.staked.0 - amount);
+    }
+
+    fn accrue(&mut self, policy: &ConvictionPolicy, now: u64) {
+        let (amount, updated_at) = self.accrued(policy, now);
+        self.amount = U128(amount);
+        self.updated_at = U64(updated_at);
+    }
+}
+
+/// Converts `[numerator, denominator]` ratio to fixed point.
+fn to_fixed(ratio: (u64, u64)) -> u128 {
+    assert!(
+        ratio.1 > 0 && ratio.0 <= ratio.1,
+        "ERR_INVALID_CONVICTION_POLICY"
+    );
+    ratio.0 as u128 * PRECISION / ratio.1 as u128
+}
+
+/// Fixed point ratio `num / den`, where `num <= den`. Drops low bits of large values to avoid overflow.
+fn ratio_of(num: Balance, den: Balance) -> u128 {
+    let shift = (128 - den.leading_zeros()).saturating_sub(64);
+    (num >> shift) * PRECISION / std::cmp::max(den >> shift, 1)
+}
+
+/// Multiplies `amount` by fixed point `ratio`, where `ratio <= PRECISION`.
+fn mul_fixed(amount: Balance, ratio: u128) -> Balance {
+    amount / PRECISION * ratio + amount % PRECISION * ratio / PRECISION
+}
+
+/// Raises fixed point `base`, where `base <= PRECISION`, to the power `exp`.
+fn pow_fixed(mut base: u128, mut exp: u64) -> u128 {
+    let mut result = PRECISION;
+    while exp > 0 && result > 0 {
+        if exp & 1 == 1 {
+            result = result * base / PRECISION;
+        }
+        base = base * base / PRECISION;
+        exp >>= 1;
+    }
+    result
+}
+
+impl Contract {
+    /// Stakes of the account per proposal, without the ones on proposals that are no longer in progress.
+    fn internal_active_conviction_stakes(&self, account_id: &AccountId) -> HashMap<u64, Balance> {
+        let mut stakes = self.conviction_stakes.get(account_id).unwrap_or_default();
+        stakes.retain(|proposal_id, _| {
+            matches!(
+                self.proposals.get(proposal_id).map(Proposal::from),
+                Some(proposal) if proposal.status == ProposalStatus::InProgress
+            )
+        });
+        stakes
+    }
+
+    /// Stakes `amount` of the account's delegated weight, not staked on other proposals, behind the proposal.
+    pub(crate) fn internal_stake_conviction(
+        &mut self,
+        policy: &Policy,
+        proposal: &mut Proposal,
+        proposal_id: u64,
+        account_id: &AccountId,
+        amount: Balance,
+    ) {
+        assert!(
+            matches!(proposal.kind, ProposalKind::ConvictionFunding { .. }),
+            "ERR_NOT_CONVICTION_FUNDING"
+        );
+        assert!(amount > 0, "ERR_ZERO_STAKE");
+        let mut stakes = self.internal_active_conviction_stakes(account_id);
+        let staked: Balance = stakes.values().sum();
+        assert!(
+            staked + amount <= self.get_user_weight(account_id),
+            "ERR_NOT_ENOUGH_WEIGHT"
+        );
+        *stakes.entry(proposal_id).or_default() += amount;
+        assert!(
+            stakes.len() <= MAX_CONVICTION_STAKES,
+            "ERR_TOO_MANY_CONVICTION_STAKES"
+        );
+        self.conviction_stakes.insert(account_id, &stakes);
+        let now = env::block_timestamp();
+        let conviction = proposal
+            .conviction
+            .get_or_insert_with(|| Conviction::new(now));
+        conviction.stake(&policy.conviction, now, amount);
+        Event::ConvictionStaked {
+            proposal_id,
+            account_id,
+            amount: U128(amount),
+            staked: conviction.staked,
+        }
+        .emit();
+    }
+
+    /// Withdraws the whole stake of the account from the proposal.
+    pub(crate) fn internal_unstake_conviction(
+        &mut self,
+        policy: &Policy,
+        proposal: &mut Proposal,
+        proposal_id: u64,
+        account_id: &AccountId,
+    ) {
+        let mut stakes = self.conviction_stakes.get(account_id).unwrap_or_default();
+        let amount = stakes
+            .remove(&proposal_id)
+            .expect("ERR_NO_CONVICTION_STAKE");
+        if stakes.is_empty() {
+            self.conviction_stakes.remove(account_id);
+        } else {
+            self.conviction_stakes.insert(account_id, &stakes);
+        }
+        if proposal.status == ProposalStatus::InProgress {
+            internal_withdraw_conviction(policy, proposal, proposal_id, account_id, amount);
+        }
+    }
+
+    /// Withdraws all stakes of the account when they exceed its delegated weight,
+    /// so undelegated tokens can't back proposals anymore.
+    pub(crate) fn internal_release_conviction_stakes(
+        &mut self,
+        account_id: &AccountId,
+        delegated: Balance,
+    ) {
+        let stakes = self.internal_active_conviction_stakes(account_id);
+        if stakes.values().sum::<Balance>() <= delegated {
+            return;
+        }
+        let policy = self.policy.get().unwrap().to_policy();
+        for (proposal_id, amount) in stakes {
+            let mut proposal: Proposal = self.proposals.get(&proposal_id).unwrap().into();
+            internal_withdraw_conviction(&policy, &mut proposal, proposal_id, account_id, amount);
+            self.proposals
+                .insert(&proposal_id, &VersionedProposal::Current(proposal));
+        }
+        self.conviction_stakes.remove(account_id);
+    }
+
+    /// Conviction needed for the `ConvictionFunding` proposal to pass, given the current treasury.
+    pub(crate) fn internal_conviction_threshold(
+        &self,
+        policy: &Policy,
+        proposal: &Proposal,
+    ) -> Option<Balance> {
+        match &proposal.kind {
+            ProposalKind::ConvictionFunding {
+                token_id, amount, ..
+            } => {
+                let treasury = match convert_old_to_new_token(token_id) {
+                    None => self.get_available_amount().0,
+                    Some(_) => env::panic_str("ERR_CONVICTION_NEAR_ONLY"),
+                };
+                policy
+                    .conviction
+                    .threshold(amount.0, treasury, self.total_delegation_amount)
+            }
+            _ => None,
+        }
+    }
+
+    /// Status of the `ConvictionFunding` proposal: approved once its conviction reaches the threshold.
+    pub(crate) fn internal_conviction_status(
+        &self,
+        policy: &Policy,
+        proposal: &Proposal,
+    ) -> ProposalStatus {
+        assert!(
+            matches!(
+                proposal.status,
+                ProposalStatus::InProgress | ProposalStatus::Failed
+            ),
+            "ERR_PROPOSAL_NOT_IN_PROGRESS"
+        );
+        let conviction = proposal.conviction.as_ref().map_or(0, |conviction| {
+            conviction.current(&policy.conviction, env::block_timestamp())
+        });
+        match self.internal_conviction_threshold(policy, proposal) {
+            Some(threshold) if conviction >= threshold => ProposalStatus::Approved,
+            _ => proposal.status.clone(),
+        }
+    }
+}
+
+fn internal_withdraw_conviction(
+    policy: &Policy,
+    proposal: &mut Proposal,
+    proposal_id: u64,
+    account_id: &AccountId,
+    amount: Balance,
+) {
+    let conviction = proposal.conviction.as_mut().expect("INTERNAL_FAIL");
+    conviction.unstake(&policy.conviction, env::block_timestamp(), amount);
+    Event::ConvictionUnstaked {
+        proposal_id,
+        account_id,
+        amount: U128(amount),
+        staked: conviction.staked,
+    }
+    .emit();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 1_000_000_000 * 60 * 60 * 24;
+
+    #[test]
+    fn test_conviction_accrual() {
+        let policy = ConvictionPolicy::default();
+        let mut conviction = Conviction::new(0);
+        conviction.stake(&policy, 0, 100);
+        assert_eq!(conviction.current(&policy, DAY - 1), 0);
+        assert_eq!(conviction.current(&policy, DAY), 10);
+        assert_eq!(conviction.current(&policy, 2 * DAY), 19);
+        // Unstaking keeps accrued conviction, which then decays.
+        conviction.unstake(&policy, 2 * DAY + DAY / 2, 100);
+        assert_eq!(conviction.updated_at, U64(2 * DAY));
+        assert_eq!(conviction.current(&policy, 3 * DAY), 17);
+        assert_eq!(conviction.current(&policy, 1000 * DAY), 0);
+    }
+
+    #[test]
+    fn test_conviction_threshold() {
+        let policy = ConvictionPolicy::default();
+        let total = 1_000_000;
+        // 0.0025 / 0.2^2 of total delegation for an infinitely small request.
+        assert_eq!(policy.threshold(1, u128::MAX, total), Some(62_500));
+        // Larger requests need more conviction.
+        assert_eq!(policy.threshold(10, 100, total), Some(250_000));
+        assert_eq!(policy.threshold(15, 100, total), Some(1_000_000));
+        assert_eq!(policy.threshold(16, 100, total), None);
+        assert_eq!(policy.threshold(20, 100, total), None);
+        assert_eq!(policy.threshold(10, 100, 0), None);
+    }
+}
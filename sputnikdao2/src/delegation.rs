@@ -1,9 +1,128 @@
+use crate::events::Event;
 use crate::*;
 
+/// Delegated amount of the account, starting from the given checkpoint.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct DelegationCheckpoint {
+    /// Id of the first proposal that sees this amount.
+    pub checkpoint_id: u64,
+    pub amount: Balance,
+    /// Id of the previous checkpoint of the account, kept in `delegation_checkpoint_history`.
+    pub prev_checkpoint_id: Option<u64>,
+}
+
+/// Delegation state recorded at proposal submission.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DelegationSnapshot {
+    /// Checkpoint to take voters' weights from.
+    pub checkpoint_id: u64,
+    /// Total delegated amount at submission.
+    pub total_amount: U128,
+    /// Sum of square roots of delegated amounts at submission, for quadratic votes.
+    pub total_quadratic_amount: U128,
+}
+
+impl DelegationSnapshot {
+    pub fn total_supply(&self) -> TotalSupply {
+        TotalSupply {
+            amount: self.total_amount.0,
+            quadratic_amount: self.total_quadratic_amount.0,
+        }
+    }
+}
+
 impl Contract {
     pub fn get_user_weight(&self, account_id: &AccountId) -> Balance {
         self.delegations.get(account_id).unwrap_or_default()
     }
+
+    /// Delegated amount of the account as of the given checkpoint.
+    /// Walks back only over the checkpoints recorded after the given one.
+    pub fn get_user_weight_at(&self, account_id: &AccountId, checkpoint_id: u64) -> Balance {
+        let mut checkpoint = match self.delegation_checkpoints.get(account_id) {
+            Some(checkpoint) => checkpoint,
+            // Delegation didn't change since checkpoints were introduced.
+            None => return self.get_user_weight(account_id),
+        };
+        while checkpoint.checkpoint_id > checkpoint_id {
+            checkpoint = match checkpoint.prev_checkpoint_id {
+                Some(prev_id) => self
+                    .delegation_checkpoint_history
+                    .get(&(account_id.clone(), prev_id))
+                    .expect("ERR_NO_CHECKPOINT"),
+                None => return 0,
+            };
+        }
+        checkpoint.amount
+    }
+
+    /// Snapshot of the current delegations for the proposal with given id.
+    pub(crate) fn internal_delegation_snapshot(&self, proposal_id: u64) -> DelegationSnapshot {
+        DelegationSnapshot {
+            checkpoint_id: proposal_id,
+            total_amount: U128(self.total_delegation_amount),
+            total_quadratic_amount: U128(self.total_quadratic_delegation_amount),
+        }
+    }
+
+    /// Current total delegated amounts.
+    pub(crate) fn internal_total_supply(&self) -> TotalSupply {
+        TotalSupply {
+            amount: self.total_delegation_amount,
+            quadratic_amount: self.total_quadratic_delegation_amount,
+        }
+    }
+
+    /// Quadratic totals are only correct once every delegation has a checkpoint.
+    pub(crate) fn assert_quadratic_weight_ready(&self, policy: &Policy, kind: &ProposalKind) {
+        assert!(
+            self.legacy_delegation_amount == 0 || !policy.uses_quadratic_weight(kind),
+            "ERR_DELEGATIONS_NOT_CHECKPOINTED"
+        );
+    }
+
+    /// Records new delegated amount of the account for proposals added from now on.
+    /// Changes in between two proposals are merged into one checkpoint.
+    /// Also updates the quadratic total, which only counts accounts with checkpoints.
+    fn internal_checkpoint_delegation(
+        &mut self,
+        account_id: &AccountId,
+        prev_amount: Balance,
+        new_amount: Balance,
+    ) {
+        let checkpoint_id = self.last_proposal_id;
+        let last = match self.delegation_checkpoints.get(account_id) {
+            Some(last) => {
+                self.total_quadratic_delegation_amount -= isqrt(prev_amount);
+                last
+            }
+            None => {
+                self.legacy_delegation_amount -= prev_amount;
+                DelegationCheckpoint {
+                    checkpoint_id: 0,
+                    amount: prev_amount,
+                    prev_checkpoint_id: None,
+                }
+            }
+        };
+        self.total_quadratic_delegation_amount += isqrt(new_amount);
+        let prev_checkpoint_id = if last.checkpoint_id == checkpoint_id {
+            last.prev_checkpoint_id
+        } else {
+            self.delegation_checkpoint_history
+                .insert(&(account_id.clone(), last.checkpoint_id), &last);
+            Some(last.checkpoint_id)
+        };
+        self.delegation_checkpoints.insert(
+            account_id,
+            &DelegationCheckpoint {
+                checkpoint_id,
+                amount: new_amount,
+                prev_checkpoint_id,
+            },
+        );
+    }
 }
 
 #[near_bindgen]
@@ -20,6 +139,28 @@ impl Contract {
         self.delegations.insert(account_id, &0);
     }
 
+    /// Records checkpoints for accounts whose delegation didn't change since the upgrade,
+    /// to count them in the quadratic total. Accounts already checkpointed are skipped.
+    pub fn checkpoint_delegations(&mut self, account_ids: Vec<AccountId>) {
+        for account_id in account_ids {
+            if self.delegation_checkpoints.get(&account_id).is_some() {
+                continue;
+            }
+            if let Some(amount) = self.delegations.get(&account_id) {
+                self.legacy_delegation_amount -= amount;
+                self.total_quadratic_delegation_amount += isqrt(amount);
+                self.delegation_checkpoints.insert(
+                    &account_id,
+                    &DelegationCheckpoint {
+                        checkpoint_id: 0,
+                        amount,
+                        prev_checkpoint_id: None,
+                    },
+                );
+            }
+        }
+    }
+
     /// Adds given amount to given account as delegated weight.
     /// Returns previous amount, new amount and total delegated amount.
     pub fn delegate(&mut self, account_id: &AccountId, amount: U128) -> (U128, U128, U128) {
@@ -35,7 +176,14 @@ impl Contract {
             .expect("ERR_NOT_REGISTERED");
         let new_amount = prev_amount + amount.0;
         self.delegations.insert(account_id, &new_amount);
+        self.internal_checkpoint_delegation(account_id, prev_amount, new_amount);
         self.total_delegation_amount += amount.0;
+        Event::DelegationUpdated {
+            account_id,
+            amount: U128(new_amount),
+            total_amount: U128(self.total_delegation_amount),
+        }
+        .emit();
         (
             U128(prev_amount),
             U128(new_amount),
@@ -56,7 +204,15 @@ impl Contract {
         assert!(prev_amount >= amount.0, "ERR_INVALID_STAKING_CONTRACT");
         let new_amount = prev_amount - amount.0;
         self.delegations.insert(account_id, &new_amount);
+        self.internal_checkpoint_delegation(account_id, prev_amount, new_amount);
+        self.internal_release_conviction_stakes(account_id, new_amount);
         self.total_delegation_amount -= amount.0;
+        Event::DelegationUpdated {
+            account_id,
+            amount: U128(new_amount),
+            total_amount: U128(self.total_delegation_amount),
+        }
+        .emit();
         (
             U128(prev_amount),
             U128(new_amount),
@@ -0,0 +1,320 @@
+//! Structured event logs following NEP-297.
+//! Every event is logged as a single line:
+//! `EVENT_JSON:{"standard":"sputnikdao","version":"1.0.0","event":"<name>","data":{...}}`.
+
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::Serialize;
+use near_sdk::{env, serde_json, AccountId};
+
+use crate::proposals::{ProposalStatus, Vote};
+
+/// Name of the event standard emitted by this contract.
+pub const EVENT_STANDARD: &str = "sputnikdao";
+
+/// Version of the events schema. Must be bumped on any change of the payloads.
+pub const EVENT_STANDARD_VERSION: &str = "1.0.0";
+
+/// Events emitted by the DAO.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum Event<'a> {
+    /// New proposal was added.
+    ProposalAdded {
+        proposal_id: u64,
+        proposer: &'a AccountId,
+        kind: &'a str,
+    },
+    /// Vote was recorded on the proposal.
+    VoteCast {
+        proposal_id: u64,
+        account_id: &'a AccountId,
+        vote: &'a Vote,
+    },
+    /// Ballot was cast on the poll.
+    BallotCast {
+        proposal_id: u64,
+        account_id: &'a AccountId,
+        ballot: &'a [u32],
+    },
+    /// Vote was retracted from the proposal.
+    VoteRetracted {
+        proposal_id: u64,
+        account_id: &'a AccountId,
+    },
+    /// Status of the proposal has changed.
+    StatusChanged {
+        proposal_id: u64,
+        status: &'a ProposalStatus,
+    },
+    /// Proposal was deleted from the storage via `RemoveProposal` action.
+    ProposalRemoved { proposal_id: u64 },
+    /// Proposal was moved to the hub DAO, where it got `hub_proposal_id`.
+    ProposalMoved {
+        proposal_id: u64,
+        hub_id: &'a AccountId,
+        hub_proposal_id: u64,
+    },
+    /// Hub DAO refused the moved proposal, it's back in progress here.
+    ProposalMoveFailed {
+        proposal_id: u64,
+        hub_id: &'a AccountId,
+    },
+    /// Proposal was successfully executed.
+    Executed { proposal_id: u64 },
+    /// Execution of the proposal has failed, proposal can be finalized again.
+    ExecutionFailed { proposal_id: u64 },
+    /// Bond was returned to the given account.
+    BondReturned {
+        proposal_id: u64,
+        account_id: &'a AccountId,
+        amount: U128,
+    },
+    /// New bounty was added.
+    BountyAdded { bounty_id: u64 },
+    /// Bounty was changed by `UpdateBounty` proposal.
+    BountyUpdated { bounty_id: u64 },
+    /// Bounty was deleted by `RemoveBounty` proposal.
+    BountyRemoved { bounty_id: u64 },
+    /// Bounty was claimed by the given account.
+    BountyClaimed {
+        bounty_id: u64,
+        account_id: &'a AccountId,
+        deadline: U64,
+    },
+    /// Given account applied for the bounty that requires assignment.
+    BountyApplied {
+        bounty_id: u64,
+        account_id: &'a AccountId,
+        deadline: U64,
+    },
+    /// Application for the bounty was rejected and its bond refunded.
+    BountyApplicationRejected {
+        bounty_id: u64,
+        account_id: &'a AccountId,
+    },
+    /// Claimer reported the bounty as done, `proposal_id` is the `BountyDone` proposal.
+    BountyDone {
+        bounty_id: u64,
+        account_id: &'a AccountId,
+        proposal_id: u64,
+    },
+    /// Claim on the bounty was removed without payout: given up or expired.
+    BountyClaimRemoved {
+        bounty_id: u64,
+        account_id: &'a AccountId,
+        bond_returned: bool,
+    },
+    /// Account staked its weight behind the conviction funding proposal.
+    ConvictionStaked {
+        proposal_id: u64,
+        account_id: &'a AccountId,
+        amount: U128,
+        staked: U128,
+    },
+    /// Account withdrew its stake from the conviction funding proposal.
+    ConvictionUnstaked {
+        proposal_id: u64,
+        account_id: &'a AccountId,
+        amount: U128,
+        staked: U128,
+    },
+    /// Tokens were deposited to the DAO via `ft_transfer_call`.
+    TokenDeposited {
+        token_id: &'a AccountId,
+        sender_id: &'a AccountId,
+        amount: U128,
+    },
+    /// NFT was deposited to the DAO via `nft_transfer_call`.
+    NftDeposited {
+        nft_contract_id: &'a AccountId,
+        token_id: &'a str,
+        sender_id: &'a AccountId,
+        previous_owner_id: &'a AccountId,
+    },
+    /// Delegated weight of the account has changed.
+    DelegationUpdated {
+        account_id: &'a AccountId,
+        amount: U128,
+        total_amount: U128,
+    },
+    /// Payout of a bulk transfer row has finished.
+    BulkTransferRowDone {
+        proposal_id: u64,
+        row: u32,
+        success: bool,
+    },
+    /// Receiver claimed vested amount of the payment stream.
+    PaymentStreamClaimed {
+        stream_id: u64,
+        receiver_id: &'a AccountId,
+        amount: U128,
+    },
+    /// Beneficiary claimed vested amount of the vesting schedule.
+    VestingClaimed {
+        schedule_id: u64,
+        beneficiary_id: &'a AccountId,
+        amount: U128,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog<'a> {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event: &'a Event<'a>,
+}
+
+impl Event<'_> {
+    /// Logs the event in NEP-297 format.
+    pub fn emit(&self) {
+        let log = EventLog {
+            standard: EVENT_STANDARD,
+            version: EVENT_STANDARD_VERSION,
+            event: self,
+        };
+        env::log_str(&format!(
+            "EVENT_JSON:{}",
+            serde_json::to_string(&log).expect("INTERNAL_FAIL")
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::json_types::U64;
+    use near_sdk::test_utils::{accounts, get_logs, VMContextBuilder};
+    use near_sdk::{testing_env, PromiseResult};
+    use near_sdk_sim::to_yocto;
+
+    use crate::test_utils::resolve_proposal;
+    use crate::*;
+
+    fn event_json(event: &str, data: &str) -> String {
+        format!(
+            r#"EVENT_JSON:{{"standard":"sputnikdao","version":"1.0.0","event":"{}","data":{}}}"#,
+            event, data
+        )
+    }
+
+    #[test]
+    fn test_proposal_lifecycle_events() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1)]),
+        );
+        testing_env!(context.attached_deposit(to_yocto("1")).build());
+        let id = contract.add_proposal(ProposalInput {
+            description: "test".to_string(),
+            kind: ProposalKind::Vote,
+        });
+        assert_eq!(
+            get_logs(),
+            vec![event_json(
+                "proposal_added",
+                r#"{"proposal_id":0,"proposer":"bob","kind":"vote"}"#
+            )]
+        );
+
+        testing_env!(context.attached_deposit(0).build());
+        contract.act_proposal(id, Action::VoteApprove, Some("lgtm".to_string()));
+        assert_eq!(
+            get_logs(),
+            vec![
+                event_json(
+                    "vote_cast",
+                    r#"{"proposal_id":0,"account_id":"bob","vote":"Approve"}"#
+                ),
+                event_json("status_changed", r#"{"proposal_id":0,"status":"Approved"}"#),
+                event_json("executed", r#"{"proposal_id":0}"#),
+                event_json(
+                    "bond_returned",
+                    r#"{"proposal_id":0,"account_id":"bob","amount":"1000000000000000000000000"}"#
+                ),
+                "Memo: lgtm".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_execution_callback_events() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1)]),
+        );
+        testing_env!(context.attached_deposit(to_yocto("1")).build());
+        let id = contract.add_proposal(ProposalInput {
+            description: "test".to_string(),
+            kind: ProposalKind::Transfer {
+                token_id: String::from(OLD_BASE_TOKEN),
+                receiver_id: accounts(2),
+                amount: U128(to_yocto("10")),
+                msg: None,
+            },
+        });
+        testing_env!(context.attached_deposit(0).build());
+        contract.act_proposal(id, Action::VoteApprove, None);
+
+        resolve_proposal(&context, &mut contract, id, PromiseResult::Failed);
+        assert_eq!(
+            get_logs(),
+            vec![
+                event_json("execution_failed", r#"{"proposal_id":0}"#),
+                event_json("status_changed", r#"{"proposal_id":0,"status":"Failed"}"#),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bounty_events() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1)]),
+        );
+        testing_env!(context.attached_deposit(to_yocto("1")).build());
+        let id = contract.add_proposal(ProposalInput {
+            description: "test".to_string(),
+            kind: ProposalKind::AddBounty {
+                bounty: Bounty {
+                    description: "test bounty".to_string(),
+                    token: String::from(OLD_BASE_TOKEN),
+                    amount: U128(to_yocto("10")),
+                    times: 1,
+                    max_deadline: U64::from(1_000),
+                    milestones: vec![],
+                    assignment: None,
+                    paused: false,
+                },
+            },
+        });
+        contract.act_proposal(id, Action::VoteApprove, None);
+        assert!(get_logs().contains(&event_json("bounty_added", r#"{"bounty_id":0}"#)));
+
+        testing_env!(context.build());
+        contract.bounty_claim(0, U64::from(500));
+        assert_eq!(
+            get_logs(),
+            vec![event_json(
+                "bounty_claimed",
+                r#"{"bounty_id":0,"account_id":"bob","deadline":"500"}"#
+            )]
+        );
+        testing_env!(context.build());
+        contract.bounty_giveup(0);
+        assert_eq!(
+            get_logs(),
+            vec![event_json(
+                "bounty_claim_removed",
+                r#"{"bounty_id":0,"account_id":"bob","bond_returned":true}"#
+            )]
+        );
+    }
+}
@@ -1,5 +1,8 @@
+use std::collections::HashMap;
+
+use near_contract_standards::non_fungible_token::TokenId;
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LazyOption, LookupMap};
+use near_sdk::collections::{LazyOption, LookupMap, UnorderedMap, UnorderedSet};
 use near_sdk::json_types::{Base58CryptoHash, U128};
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::{
@@ -7,22 +10,53 @@ use near_sdk::{
     PanicOnDefault, Promise, PromiseResult,
 };
 
-pub use crate::bounties::{Bounty, BountyClaim, VersionedBounty};
+pub use crate::bounties::{
+    Bounty, BountyApplication, BountyAssignment, BountyClaim, BountyMilestone, BountyUpdate,
+    BountyV1, VersionedBounty,
+};
+pub use crate::bulk::{BulkTransferRow, BulkTransferStatus, Payout};
+pub use crate::conviction::{Conviction, ConvictionPolicy};
+pub use crate::delegation::{DelegationCheckpoint, DelegationSnapshot};
 pub use crate::policy::{
-    default_policy, Policy, RoleKind, RolePermission, VersionedPolicy, VotePolicy,
+    default_policy, isqrt, Policy, RoleKind, RolePermission, TotalSupply, VersionedPolicy,
+    VotePolicy,
 };
+pub use crate::poll::{PollTally, PollVotes};
+pub use crate::pools::PoolDeposit;
 use crate::proposals::VersionedProposal;
-pub use crate::proposals::{Proposal, ProposalInput, ProposalKind, ProposalStatus};
+pub use crate::proposals::{Proposal, ProposalInput, ProposalKind, ProposalLink, ProposalStatus};
+pub use crate::spending::SpendingLimit;
+use crate::spending::SpendingRecord;
+pub use crate::streams::PaymentStream;
+pub use crate::swap::{Swap, SwapStep};
+pub use crate::treasury::{FtPayout, TokenBalance};
 pub use crate::types::{Action, Config, OldAccountId, OLD_BASE_TOKEN};
 use crate::upgrade::{internal_get_factory_info, internal_set_factory_info, FactoryInfo};
-pub use crate::views::{BountyOutput, ProposalOutput};
+pub use crate::vesting::{Vesting, VestingSchedule};
+pub use crate::views::{
+    BountyClaimOutput, BountyOutput, ConvictionOutput, NftOutput, ProposalOutput,
+    SpendingLimitOutput, StakingPoolOutput, TreasuryOutput,
+};
 
 mod bounties;
+mod bulk;
+mod conviction;
 mod delegation;
+pub mod events;
+mod nft;
 mod policy;
+mod poll;
+mod pools;
 mod proposals;
+mod spending;
+mod streams;
+mod swap;
+#[cfg(test)]
+mod test_utils;
+mod treasury;
 mod types;
 mod upgrade;
+mod vesting;
 pub mod views;
 
 #[derive(BorshStorageKey, BorshSerialize)]
@@ -35,6 +69,24 @@ pub enum StorageKeys {
     BountyClaimers,
     BountyClaimCounts,
     Blobs,
+    DelegationCheckpoints,
+    ConvictionStakes,
+    Spending,
+    Tokens,
+    Nfts,
+    StakingPools,
+    BulkTransfers,
+    PaymentStreams,
+    Reserves,
+    VestingSchedules,
+    BeneficiaryVestings,
+    BountyClaimMilestones,
+    BountyApplications,
+    BountyClaimsIndex,
+    DelegationCheckpointHistory,
+    BountyReserves,
+    BountyPartialClaims,
+    NftTokens(AccountId),
 }
 
 /// After payouts, allows a callback
@@ -42,6 +94,20 @@ pub enum StorageKeys {
 pub trait ExtSelf {
     /// Callback after proposal execution.
     fn on_proposal_callback(&mut self, proposal_id: u64) -> PromiseOrValue<()>;
+    /// Callback after moving proposal to the hub.
+    fn on_move_proposal_callback(&mut self, proposal_id: u64, hub_id: AccountId, bond: U128);
+    /// Callback with the balance of the DAO in the token.
+    fn on_ft_balance_callback(&mut self, token_id: AccountId) -> U128;
+    /// Callback with the storage balance of the receiver of a token payout.
+    fn on_storage_balance_callback(&mut self, payout: FtPayout) -> Promise;
+    /// Callback after the payout of a bulk transfer row.
+    fn on_bulk_transfer_callback(&mut self, proposal_id: u64, row: u32);
+    /// Callback after the payout of a payment stream claim.
+    fn on_stream_claim_callback(&mut self, stream_id: u64, amount: U128);
+    /// Callback after the payout of a vesting claim.
+    fn on_vesting_claim_callback(&mut self, schedule_id: u64, amount: U128);
+    /// Callback after each step of the swap on the exchange.
+    fn on_swap_callback(&mut self, swap: Swap, step: SwapStep) -> PromiseOrValue<bool>;
 }
 
 #[near_bindgen]
@@ -78,6 +144,68 @@ pub struct Contract {
 
     /// Large blob storage.
     pub blobs: LookupMap<CryptoHash, AccountId>,
+
+    /// Latest checkpoint of delegated amount per user, used to evaluate votes as of proposal submission.
+    pub delegation_checkpoints: LookupMap<AccountId, DelegationCheckpoint>,
+    /// Sum of square roots of delegated amounts, for quadratic votes.
+    /// Delegations that didn't change since checkpoints were introduced are not included.
+    pub total_quadratic_delegation_amount: Balance,
+    /// Delegated amount of accounts without checkpoints, missing from the quadratic total.
+    /// Quadratic votes are blocked until it's zero, see `checkpoint_delegations`.
+    pub legacy_delegation_amount: Balance,
+    /// Weight each account has staked per conviction funding proposal.
+    pub conviction_stakes: LookupMap<AccountId, HashMap<u64, Balance>>,
+    /// Recent payouts of transfers per token, for spending limits.
+    pub spending: LookupMap<OldAccountId, Vec<SpendingRecord>>,
+    /// Fungible tokens held by the DAO with their cached balances.
+    pub tokens: UnorderedMap<AccountId, TokenBalance>,
+    /// Token ids of NFTs held by the DAO per NFT contract.
+    pub nfts: UnorderedMap<AccountId, UnorderedSet<TokenId>>,
+    /// $NEAR deposited with staking pools per pool.
+    pub staking_pools: UnorderedMap<AccountId, PoolDeposit>,
+    /// Rows of approved bulk transfers with their status per proposal.
+    pub bulk_transfers: LookupMap<u64, Vec<BulkTransferRow>>,
+    /// Payment streams by id of the proposal that created them.
+    pub payment_streams: UnorderedMap<u64, PaymentStream>,
+    /// Amount per token that payment streams and vesting schedules can still pay out.
+    pub reserves: LookupMap<OldAccountId, Balance>,
+    /// Vesting schedules by id of the proposal that created them.
+    pub vesting_schedules: LookupMap<u64, VestingSchedule>,
+    /// Ids of the vesting schedules per beneficiary.
+    pub beneficiary_vestings: LookupMap<AccountId, Vec<u64>>,
+    /// Milestones done so far per bounty claim, for bounties with milestones.
+    pub bounty_claim_milestones: LookupMap<(u64, AccountId), Vec<u32>>,
+    /// Pending applications per bounty, for bounties that require assignment.
+    pub bounty_applications: LookupMap<u64, Vec<BountyApplication>>,
+    /// Accounts with a claim in progress per bounty.
+    /// Claims made before the index was introduced are added with `index_bounty_claims`.
+    pub bounty_claims_index: LookupMap<u64, Vec<AccountId>>,
+    /// Earlier delegation checkpoints per user and checkpoint id.
+    pub delegation_checkpoint_history: LookupMap<(AccountId, u64), DelegationCheckpoint>,
+    /// Funds still reserved per bounty, released as it's paid out or removed.
+    /// Bounties added before the upgrade have no entry until reserved with `reserve_bounties`.
+    pub bounty_reserves: LookupMap<u64, Balance>,
+    /// Milestones paid out to claims that ended before they were done, per bounty.
+    /// Next claims take them over, so the bounty isn't paid more than its amount per time.
+    pub bounty_partial_claims: LookupMap<u64, Vec<Vec<u32>>>,
+}
+
+/// Contract state of the previous release, used for migration.
+#[derive(BorshDeserialize)]
+pub struct OldContract {
+    pub config: LazyOption<Config>,
+    pub policy: LazyOption<VersionedPolicy>,
+    pub locked_amount: Balance,
+    pub staking_id: Option<AccountId>,
+    pub total_delegation_amount: Balance,
+    pub delegations: LookupMap<AccountId, Balance>,
+    pub last_proposal_id: u64,
+    pub proposals: LookupMap<u64, VersionedProposal>,
+    pub last_bounty_id: u64,
+    pub bounties: LookupMap<u64, VersionedBounty>,
+    pub bounty_claimers: LookupMap<AccountId, Vec<BountyClaim>>,
+    pub bounty_claims_count: LookupMap<u64, u32>,
+    pub blobs: LookupMap<CryptoHash, AccountId>,
 }
 
 #[near_bindgen]
@@ -98,6 +226,25 @@ impl Contract {
             bounty_claims_count: LookupMap::new(StorageKeys::BountyClaimCounts),
             blobs: LookupMap::new(StorageKeys::Blobs),
             locked_amount: 0,
+            delegation_checkpoints: LookupMap::new(StorageKeys::DelegationCheckpoints),
+            total_quadratic_delegation_amount: 0,
+            legacy_delegation_amount: 0,
+            conviction_stakes: LookupMap::new(StorageKeys::ConvictionStakes),
+            spending: LookupMap::new(StorageKeys::Spending),
+            tokens: UnorderedMap::new(StorageKeys::Tokens),
+            nfts: UnorderedMap::new(StorageKeys::Nfts),
+            staking_pools: UnorderedMap::new(StorageKeys::StakingPools),
+            bulk_transfers: LookupMap::new(StorageKeys::BulkTransfers),
+            payment_streams: UnorderedMap::new(StorageKeys::PaymentStreams),
+            reserves: LookupMap::new(StorageKeys::Reserves),
+            vesting_schedules: LookupMap::new(StorageKeys::VestingSchedules),
+            beneficiary_vestings: LookupMap::new(StorageKeys::BeneficiaryVestings),
+            bounty_claim_milestones: LookupMap::new(StorageKeys::BountyClaimMilestones),
+            bounty_applications: LookupMap::new(StorageKeys::BountyApplications),
+            bounty_claims_index: LookupMap::new(StorageKeys::BountyClaimsIndex),
+            delegation_checkpoint_history: LookupMap::new(StorageKeys::DelegationCheckpointHistory),
+            bounty_reserves: LookupMap::new(StorageKeys::BountyReserves),
+            bounty_partial_claims: LookupMap::new(StorageKeys::BountyPartialClaims),
         };
         internal_set_factory_info(&FactoryInfo {
             factory_id: env::predecessor_account_id(),
@@ -107,14 +254,49 @@ impl Contract {
     }
 
     /// Should only be called by this contract on migration.
-    /// This is NOOP implementation. KEEP IT if you haven't changed contract state.
-    /// If you have changed state, you need to implement migration from old state (keep the old struct with different name to deserialize it first).
-    /// After migrate goes live on MainNet, return this implementation for next updates.
+    /// Migrates from `OldContract` state, initializing the fields added since.
+    /// After migrate goes live on MainNet, return NOOP implementation for next updates.
     #[private]
     #[init(ignore_state)]
     pub fn migrate() -> Self {
-        let this: Contract = env::state_read().expect("ERR_CONTRACT_IS_NOT_INITIALIZED");
-        this
+        let old: OldContract = env::state_read().expect("ERR_CONTRACT_IS_NOT_INITIALIZED");
+        Self {
+            config: old.config,
+            policy: old.policy,
+            locked_amount: old.locked_amount,
+            staking_id: old.staking_id,
+            total_delegation_amount: old.total_delegation_amount,
+            delegations: old.delegations,
+            last_proposal_id: old.last_proposal_id,
+            proposals: old.proposals,
+            last_bounty_id: old.last_bounty_id,
+            bounties: old.bounties,
+            bounty_claimers: old.bounty_claimers,
+            bounty_claims_count: old.bounty_claims_count,
+            blobs: old.blobs,
+            delegation_checkpoints: LookupMap::new(StorageKeys::DelegationCheckpoints),
+            total_quadratic_delegation_amount: 0,
+            legacy_delegation_amount: old.total_delegation_amount,
+            conviction_stakes: LookupMap::new(StorageKeys::ConvictionStakes),
+            spending: LookupMap::new(StorageKeys::Spending),
+            tokens: UnorderedMap::new(StorageKeys::Tokens),
+            nfts: UnorderedMap::new(StorageKeys::Nfts),
+            staking_pools: UnorderedMap::new(StorageKeys::StakingPools),
+            bulk_transfers: LookupMap::new(StorageKeys::BulkTransfers),
+            payment_streams: UnorderedMap::new(StorageKeys::PaymentStreams),
+            reserves: LookupMap::new(StorageKeys::Reserves),
+            vesting_schedules: LookupMap::new(StorageKeys::VestingSchedules),
+            beneficiary_vestings: LookupMap::new(StorageKeys::BeneficiaryVestings),
+            bounty_claim_milestones: LookupMap::new(StorageKeys::BountyClaimMilestones),
+            bounty_applications: LookupMap::new(StorageKeys::BountyApplications),
+            // Existing claims can't be listed here as they are stored per claimer,
+            // they are indexed afterwards with `index_bounty_claims`.
+            bounty_claims_index: LookupMap::new(StorageKeys::BountyClaimsIndex),
+            delegation_checkpoint_history: LookupMap::new(StorageKeys::DelegationCheckpointHistory),
+            // Funds of the open bounties are reserved afterwards with `reserve_bounties`.
+            bounty_reserves: LookupMap::new(StorageKeys::BountyReserves),
+            bounty_partial_claims: LookupMap::new(StorageKeys::BountyPartialClaims),
+        }
     }
 
     /// Remove blob from contract storage and pay back to original storer.
@@ -173,11 +355,14 @@ pub extern "C" fn store_blob() {
 
 #[cfg(test)]
 mod tests {
+    use near_sdk::json_types::U64;
     use near_sdk::test_utils::{accounts, VMContextBuilder};
     use near_sdk::testing_env;
     use near_sdk_sim::to_yocto;
 
-    use crate::proposals::ProposalStatus;
+    use crate::policy::{QuorumKind, RoleCombination, WeightKind};
+    use crate::proposals::{ProposalStatus, Vote};
+    use crate::test_utils::promise_result;
 
     use super::*;
 
@@ -318,6 +503,576 @@ mod tests {
         assert_eq!(x.roles.len(), 2);
     }
 
+    #[test]
+    fn test_move_to_hub() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut policy = VersionedPolicy::Default(vec![accounts(1).into()]).upgrade();
+        policy.to_policy_mut().roles[1]
+            .permissions
+            .insert("*:MoveToHub".to_string());
+        let mut contract = Contract::new(Config::test_config(), policy);
+        let id = create_proposal(&mut context, &mut contract);
+        assert_eq!(contract.locked_amount, to_yocto("1"));
+
+        contract.act_proposal(
+            id,
+            Action::MoveToHub {
+                hub_id: accounts(3),
+            },
+            None,
+        );
+        assert_eq!(
+            contract.get_proposal(id).proposal.status,
+            ProposalStatus::Moved
+        );
+        assert_eq!(contract.locked_amount, 0);
+
+        // Hub refused the proposal: bond is locked again and proposal is back in progress.
+        promise_result(&context, PromiseResult::Failed);
+        contract.on_move_proposal_callback(id, accounts(3), U128(to_yocto("1")));
+        assert_eq!(
+            contract.get_proposal(id).proposal.status,
+            ProposalStatus::InProgress
+        );
+        assert_eq!(contract.locked_amount, to_yocto("1"));
+
+        contract.act_proposal(
+            id,
+            Action::MoveToHub {
+                hub_id: accounts(3),
+            },
+            None,
+        );
+        promise_result(&context, PromiseResult::Successful(b"5".to_vec()));
+        contract.on_move_proposal_callback(id, accounts(3), U128(to_yocto("1")));
+        let proposal = contract.get_proposal(id).proposal;
+        assert_eq!(proposal.status, ProposalStatus::Moved);
+        assert_eq!(
+            proposal.moved_to,
+            Some(ProposalLink {
+                dao_id: accounts(3),
+                proposal_id: 5
+            })
+        );
+    }
+
+    fn create_timelocked_contract(context: &mut VMContextBuilder) -> Contract {
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut policy = VersionedPolicy::Default(vec![accounts(1).into()]).upgrade();
+        policy
+            .to_policy_mut()
+            .execution_delay
+            .insert("transfer".to_string(), U64::from(1_000));
+        policy.to_policy_mut().roles[1]
+            .permissions
+            .insert("*:Veto".to_string());
+        Contract::new(Config::test_config(), policy)
+    }
+
+    #[test]
+    fn test_execution_delay() {
+        let mut context = VMContextBuilder::new();
+        let mut contract = create_timelocked_contract(&mut context);
+        let id = create_proposal(&mut context, &mut contract);
+        contract.act_proposal(id, Action::VoteApprove, None);
+        let proposal = contract.get_proposal(id).proposal;
+        assert_eq!(proposal.status, ProposalStatus::Queued);
+        assert_eq!(proposal.timelock_end, Some(U64::from(1_000)));
+
+        // Anyone can execute once the delay has passed.
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(1_000)
+            .build());
+        contract.act_proposal(id, Action::Execute, None);
+        assert_eq!(
+            contract.get_proposal(id).proposal.status,
+            ProposalStatus::Approved
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_PROPOSAL_TIMELOCKED")]
+    fn test_execute_before_delay() {
+        let mut context = VMContextBuilder::new();
+        let mut contract = create_timelocked_contract(&mut context);
+        let id = create_proposal(&mut context, &mut contract);
+        contract.act_proposal(id, Action::VoteApprove, None);
+        testing_env!(context.block_timestamp(999).build());
+        contract.act_proposal(id, Action::Execute, None);
+    }
+
+    #[test]
+    fn test_veto() {
+        let mut context = VMContextBuilder::new();
+        let mut contract = create_timelocked_contract(&mut context);
+        let id = create_proposal(&mut context, &mut contract);
+        contract.act_proposal(id, Action::VoteApprove, None);
+        contract.act_proposal(id, Action::Veto, None);
+        assert_eq!(
+            contract.get_proposal(id).proposal.status,
+            ProposalStatus::Rejected
+        );
+        assert_eq!(contract.locked_amount, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_PERMISSION_DENIED")]
+    fn test_veto_denied() {
+        let mut context = VMContextBuilder::new();
+        let mut contract = create_timelocked_contract(&mut context);
+        let id = create_proposal(&mut context, &mut contract);
+        contract.act_proposal(id, Action::VoteApprove, None);
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.act_proposal(id, Action::Veto, None);
+    }
+
+    #[test]
+    fn test_vote_abstain() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut policy =
+            VersionedPolicy::Default(vec![accounts(1), accounts(2), accounts(3)]).upgrade();
+        let vote_policy = &mut policy.to_policy_mut().default_vote_policy;
+        vote_policy.quorum = U128(2);
+        vote_policy.quorum_kind = QuorumKind::Participation;
+        let mut contract = Contract::new(Config::test_config(), policy);
+        let id = create_proposal(&mut context, &mut contract);
+
+        contract.act_proposal(id, Action::VoteAbstain, None);
+        let proposal = contract.get_proposal(id).proposal;
+        assert_eq!(proposal.vote_counts["council"], [0, 0, 0, 1]);
+        assert_eq!(proposal.status, ProposalStatus::InProgress);
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.act_proposal(id, Action::VoteApprove, None);
+        assert_eq!(
+            contract.get_proposal(id).proposal.status,
+            ProposalStatus::Approved
+        );
+    }
+
+    #[test]
+    fn test_bicameral_approval() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut policy = VersionedPolicy::Default(vec![accounts(1)]).upgrade();
+        let mut experts = policy.to_policy_mut().roles[1].clone();
+        experts.name = "experts".to_string();
+        experts.kind = RoleKind::Group(vec![accounts(2)].into_iter().collect());
+        policy.to_policy_mut().roles.push(experts);
+        policy.to_policy_mut().roles[1].vote_policy.insert(
+            "transfer".to_string(),
+            VotePolicy {
+                combination: Some(RoleCombination::AllOf(vec![
+                    "council".to_string(),
+                    "experts".to_string(),
+                ])),
+                ..VotePolicy::default()
+            },
+        );
+        let mut contract = Contract::new(Config::test_config(), policy);
+
+        // Council approval alone is not enough.
+        let id = create_proposal(&mut context, &mut contract);
+        contract.act_proposal(id, Action::VoteApprove, None);
+        assert_eq!(
+            contract.get_proposal(id).proposal.status,
+            ProposalStatus::InProgress
+        );
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.act_proposal(id, Action::VoteApprove, None);
+        assert_eq!(
+            contract.get_proposal(id).proposal.status,
+            ProposalStatus::Approved
+        );
+
+        // Other chamber rejecting rejects the proposal.
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let id = create_proposal(&mut context, &mut contract);
+        contract.act_proposal(id, Action::VoteApprove, None);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.act_proposal(id, Action::VoteReject, None);
+        assert_eq!(
+            contract.get_proposal(id).proposal.status,
+            ProposalStatus::Rejected
+        );
+    }
+
+    #[test]
+    fn test_change_vote() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut policy =
+            VersionedPolicy::Default(vec![accounts(1), accounts(2), accounts(3)]).upgrade();
+        policy.to_policy_mut().allow_vote_change = true;
+        let mut contract = Contract::new(Config::test_config(), policy);
+        let id = create_proposal(&mut context, &mut contract);
+
+        contract.act_proposal(id, Action::VoteApprove, None);
+        contract.act_proposal(id, Action::VoteReject, None);
+        let proposal = contract.get_proposal(id).proposal;
+        assert_eq!(proposal.vote_counts["council"], [0, 1, 0, 0]);
+        assert!(matches!(proposal.votes[&accounts(1)], Vote::Reject));
+
+        contract.act_proposal(id, Action::RetractVote, None);
+        let proposal = contract.get_proposal(id).proposal;
+        assert_eq!(proposal.vote_counts["council"], [0, 0, 0, 0]);
+        assert!(proposal.votes.is_empty());
+        assert_eq!(proposal.status, ProposalStatus::InProgress);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_VOTE_CHANGE_NOT_ALLOWED")]
+    fn test_retract_vote_not_allowed() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1), accounts(2)]),
+        );
+        let id = create_proposal(&mut context, &mut contract);
+        contract.act_proposal(id, Action::VoteApprove, None);
+        contract.act_proposal(id, Action::RetractVote, None);
+    }
+
+    #[test]
+    fn test_ranked_poll() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1), accounts(2), accounts(3)]),
+        );
+        testing_env!(context.attached_deposit(to_yocto("1")).build());
+        let id = contract.add_proposal(ProposalInput {
+            description: "pick a logo".to_string(),
+            kind: ProposalKind::Poll {
+                options: vec!["red".to_string(), "green".to_string(), "blue".to_string()],
+                tally: PollTally::InstantRunoff,
+            },
+        });
+        contract.act_proposal(id, Action::CastBallot { ballot: vec![2, 0] }, None);
+        let proposal = contract.get_proposal(id).proposal;
+        assert_eq!(proposal.status, ProposalStatus::InProgress);
+        assert_eq!(proposal.poll.unwrap().tallies["council"], vec![0, 0, 1]);
+
+        // Option 1 is eliminated first, then option 2 and its ballot moves to option 0.
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.act_proposal(id, Action::CastBallot { ballot: vec![0, 1] }, None);
+        let proposal = contract.get_proposal(id).proposal;
+        assert_eq!(proposal.status, ProposalStatus::Approved);
+        assert_eq!(proposal.poll.unwrap().winner, Some(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_POLL_NEEDS_BALLOT")]
+    fn test_poll_approve_vote() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1)]),
+        );
+        testing_env!(context.attached_deposit(to_yocto("1")).build());
+        let id = contract.add_proposal(ProposalInput {
+            description: "pick a date".to_string(),
+            kind: ProposalKind::Poll {
+                options: vec!["monday".to_string(), "friday".to_string()],
+                tally: PollTally::Plurality,
+            },
+        });
+        contract.act_proposal(id, Action::VoteApprove, None);
+    }
+
+    #[test]
+    fn test_quadratic_vote() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let council = vec![accounts(0), accounts(1), accounts(2), accounts(3)];
+        let mut policy = VersionedPolicy::Default(council.clone()).upgrade();
+        policy.to_policy_mut().default_vote_policy.weight_kind = WeightKind::QuadraticTokenWeight;
+        let mut contract = Contract::new(Config::test_config(), policy);
+        contract.staking_id = Some(accounts(4));
+        // Whale has the majority of tokens, but not of their square roots.
+        let whale = to_yocto("120");
+        let small = to_yocto("25");
+        for (account_id, amount) in council.iter().zip([whale, small, small, small]) {
+            testing_env!(context
+                .predecessor_account_id(accounts(4))
+                .attached_deposit(16 * env::storage_byte_cost())
+                .build());
+            contract.register_delegation(account_id);
+            contract.delegate(account_id, U128(amount));
+        }
+        assert_eq!(
+            contract.total_quadratic_delegation_amount,
+            isqrt(whale) + 3 * isqrt(small)
+        );
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        let id = create_proposal(&mut context, &mut contract);
+        testing_env!(context.attached_deposit(0).build());
+        contract.act_proposal(id, Action::VoteApprove, None);
+        let proposal = contract.get_proposal(id).proposal;
+        assert_eq!(proposal.vote_counts["council"], [isqrt(whale), 0, 0, 0]);
+        assert_eq!(proposal.status, ProposalStatus::InProgress);
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.act_proposal(id, Action::VoteApprove, None);
+        assert_eq!(
+            contract.get_proposal(id).proposal.status,
+            ProposalStatus::Approved
+        );
+    }
+
+    /// Sets up quadratic voting with delegations made before checkpoints were introduced.
+    fn setup_legacy_delegations(context: &mut VMContextBuilder) -> Contract {
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut policy = VersionedPolicy::Default(vec![accounts(1)]).upgrade();
+        policy.to_policy_mut().default_vote_policy.weight_kind = WeightKind::QuadraticTokenWeight;
+        let mut contract = Contract::new(Config::test_config(), policy);
+        contract.staking_id = Some(accounts(4));
+        for account_id in [accounts(1), accounts(2)] {
+            contract.delegations.insert(&account_id, &100);
+        }
+        contract.total_delegation_amount = 200;
+        contract.legacy_delegation_amount = 200;
+        contract
+    }
+
+    #[test]
+    fn test_checkpoint_legacy_delegations() {
+        let mut context = VMContextBuilder::new();
+        let mut contract = setup_legacy_delegations(&mut context);
+        contract.checkpoint_delegations(vec![accounts(1), accounts(1), accounts(3)]);
+        assert_eq!(contract.legacy_delegation_amount, 100);
+        contract.checkpoint_delegations(vec![accounts(2)]);
+        assert_eq!(contract.legacy_delegation_amount, 0);
+        assert_eq!(contract.total_quadratic_delegation_amount, 2 * isqrt(100));
+
+        let id = create_proposal(&mut context, &mut contract);
+        testing_env!(context.attached_deposit(0).build());
+        contract.act_proposal(id, Action::VoteApprove, None);
+        assert_eq!(
+            contract.get_proposal(id).proposal.vote_counts["council"],
+            [isqrt(100), 0, 0, 0]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_DELEGATIONS_NOT_CHECKPOINTED")]
+    fn test_quadratic_vote_legacy_delegations() {
+        let mut context = VMContextBuilder::new();
+        let mut contract = setup_legacy_delegations(&mut context);
+        contract.checkpoint_delegations(vec![accounts(1)]);
+        create_proposal(&mut context, &mut contract);
+    }
+
+    #[test]
+    fn test_vote_weight_snapshot() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut policy = VersionedPolicy::Default(vec![accounts(1).into()]).upgrade();
+        policy.to_policy_mut().default_vote_policy.weight_kind = WeightKind::TokenWeight;
+        let mut contract = Contract::new(Config::test_config(), policy);
+        contract.staking_id = Some(accounts(4));
+
+        let delegate = |context: &mut VMContextBuilder, contract: &mut Contract, amount| {
+            testing_env!(context
+                .predecessor_account_id(accounts(4))
+                .attached_deposit(16 * env::storage_byte_cost())
+                .build());
+            if contract.delegations.get(&accounts(1)).is_none() {
+                contract.register_delegation(&accounts(1));
+            }
+            contract.delegate(&accounts(1), U128(amount));
+            testing_env!(context.predecessor_account_id(accounts(1)).build());
+        };
+        delegate(&mut context, &mut contract, 10);
+        let id = create_proposal(&mut context, &mut contract);
+        delegate(&mut context, &mut contract, 100);
+        delegate(&mut context, &mut contract, 1000);
+        create_proposal(&mut context, &mut contract);
+        delegate(&mut context, &mut contract, 10000);
+
+        assert_eq!(contract.delegation_balance_at(accounts(1), id).0, 10);
+        assert_eq!(contract.delegation_balance_at(accounts(1), id + 1).0, 1110);
+        assert_eq!(contract.delegation_balance_at(accounts(1), id + 2).0, 11110);
+        // Only the latest checkpoint is kept per account, earlier ones are looked up by id.
+        assert_eq!(
+            contract
+                .delegation_checkpoints
+                .get(&accounts(1))
+                .unwrap()
+                .checkpoint_id,
+            id + 2
+        );
+        assert!(contract
+            .delegation_checkpoint_history
+            .get(&(accounts(1), id + 1))
+            .is_some());
+        assert_eq!(
+            contract.get_proposal(id).proposal.snapshot,
+            Some(DelegationSnapshot {
+                checkpoint_id: id,
+                total_amount: U128(10),
+                total_quadratic_amount: U128(isqrt(10)),
+            })
+        );
+
+        testing_env!(context.attached_deposit(0).build());
+        contract.act_proposal(id, Action::VoteApprove, None);
+        assert_eq!(
+            contract.get_proposal(id).proposal.vote_counts["council"],
+            [10, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn test_conviction_funding() {
+        let day = 1_000_000_000 * 60 * 60 * 24;
+        let mut context = VMContextBuilder::new();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .account_balance(to_yocto("1000"))
+            .build());
+        let mut policy = VersionedPolicy::Default(vec![accounts(1).into()]).upgrade();
+        policy.to_policy_mut().roles[0]
+            .permissions
+            .insert("conviction_funding:*".to_string());
+        let mut contract = Contract::new(Config::test_config(), policy);
+        contract.staking_id = Some(accounts(4));
+        testing_env!(context
+            .predecessor_account_id(accounts(4))
+            .attached_deposit(16 * env::storage_byte_cost())
+            .build());
+        contract.register_delegation(&accounts(1));
+        contract.delegate(&accounts(1), U128(100));
+
+        let add_funding = |context: &mut VMContextBuilder, contract: &mut Contract| {
+            testing_env!(context
+                .predecessor_account_id(accounts(1))
+                .attached_deposit(to_yocto("1"))
+                .build());
+            let id = contract.add_proposal(ProposalInput {
+                description: "grant".to_string(),
+                kind: ProposalKind::ConvictionFunding {
+                    token_id: String::from(OLD_BASE_TOKEN),
+                    receiver_id: accounts(2),
+                    amount: U128(to_yocto("10")),
+                },
+            });
+            testing_env!(context.attached_deposit(0).build());
+            id
+        };
+        let id = add_funding(&mut context, &mut contract);
+        contract.act_proposal(id, Action::StakeConviction { amount: U128(100) }, None);
+        // Requesting ~1% of the treasury needs conviction of ~6.9% of delegated weight.
+        assert_eq!(
+            contract.get_conviction(id),
+            ConvictionOutput {
+                staked: U128(100),
+                conviction: U128(0),
+                threshold: Some(U128(6)),
+            }
+        );
+
+        // Conviction grows by a tenth of the stake in the first day and passes the threshold.
+        testing_env!(context.block_timestamp(day).build());
+        assert_eq!(contract.get_conviction(id).conviction, U128(10));
+        contract.act_proposal(id, Action::Finalize, None);
+        assert_eq!(
+            contract.get_proposal(id).proposal.status,
+            ProposalStatus::Approved
+        );
+
+        // Stake on the approved proposal is free again. Undelegating below staked weight withdraws stakes.
+        let id = add_funding(&mut context, &mut contract);
+        contract.act_proposal(id, Action::StakeConviction { amount: U128(60) }, None);
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.undelegate(&accounts(1), U128(50));
+        assert_eq!(contract.get_conviction(id).staked, U128(0));
+        assert!(contract.conviction_stakes.get(&accounts(1)).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ENOUGH_WEIGHT")]
+    fn test_conviction_stake_above_weight() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut policy = VersionedPolicy::Default(vec![accounts(1).into()]).upgrade();
+        policy.to_policy_mut().roles[1]
+            .permissions
+            .insert("*:StakeConviction".to_string());
+        let mut contract = Contract::new(Config::test_config(), policy);
+        testing_env!(context.attached_deposit(to_yocto("1")).build());
+        let id = contract.add_proposal(ProposalInput {
+            description: "grant".to_string(),
+            kind: ProposalKind::ConvictionFunding {
+                token_id: String::from(OLD_BASE_TOKEN),
+                receiver_id: accounts(2),
+                amount: U128(to_yocto("10")),
+            },
+        });
+        contract.act_proposal(id, Action::StakeConviction { amount: U128(1) }, None);
+    }
+
+    #[test]
+    fn test_add_moved_proposal() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut policy = VersionedPolicy::Default(vec![accounts(1).into()]).upgrade();
+        policy.to_policy_mut().moved_proposal_sources = vec![accounts(3)];
+        let mut contract = Contract::new(Config::test_config(), policy);
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(to_yocto("1"))
+            .build());
+        let id = contract.add_moved_proposal(
+            ProposalInput {
+                description: "test".to_string(),
+                kind: ProposalKind::Vote,
+            },
+            accounts(2),
+            7,
+        );
+        let proposal = contract.get_proposal(id).proposal;
+        assert_eq!(proposal.proposer, accounts(2));
+        assert_eq!(
+            proposal.moved_from,
+            Some(ProposalLink {
+                dao_id: accounts(3),
+                proposal_id: 7
+            })
+        );
+        assert_eq!(contract.locked_amount, to_yocto("1"));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_MOVED_PROPOSAL_SOURCE")]
+    fn test_add_moved_proposal_unknown_source() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1).into()]),
+        );
+        testing_env!(context
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(to_yocto("1"))
+            .build());
+        contract.add_moved_proposal(
+            ProposalInput {
+                description: "test".to_string(),
+                kind: ProposalKind::Vote,
+            },
+            accounts(2),
+            7,
+        );
+    }
+
     #[test]
     #[should_panic(expected = "ERR_INVALID_POLICY")]
     fn test_fails_adding_invalid_policy() {
@@ -0,0 +1,205 @@
+//! Inventory of non-fungible tokens (NEP-171) held by the DAO.
+//! Only tokens sent with `nft_transfer_call` from contracts listed in `treasury_nft_contracts` of the policy
+//! are recorded, `TransferNft` proposals can still send others.
+
+use near_contract_standards::non_fungible_token::core::NonFungibleTokenReceiver;
+use near_contract_standards::non_fungible_token::TokenId;
+use near_sdk::collections::UnorderedSet;
+use near_sdk::serde_json::json;
+use near_sdk::{log, PromiseOrValue};
+
+use crate::events::Event;
+use crate::types::{GAS_FOR_NFT_TRANSFER, GAS_FOR_NFT_TRANSFER_CALL, ONE_YOCTO_NEAR};
+use crate::*;
+
+impl Contract {
+    /// Sends the NFT to `receiver_id`, with `nft_transfer_call` if `msg` is given.
+    pub(crate) fn internal_transfer_nft(
+        &self,
+        nft_contract_id: &AccountId,
+        token_id: &TokenId,
+        receiver_id: &AccountId,
+        memo: String,
+        msg: Option<String>,
+    ) -> Promise {
+        let promise = Promise::new(nft_contract_id.clone());
+        match msg {
+            Some(msg) => promise.function_call(
+                "nft_transfer_call".to_string(),
+                json!({
+                    "receiver_id": receiver_id,
+                    "token_id": token_id,
+                    "memo": memo,
+                    "msg": msg,
+                })
+                .to_string()
+                .into_bytes(),
+                ONE_YOCTO_NEAR,
+                GAS_FOR_NFT_TRANSFER_CALL,
+            ),
+            None => promise.function_call(
+                "nft_transfer".to_string(),
+                json!({
+                    "receiver_id": receiver_id,
+                    "token_id": token_id,
+                    "memo": memo,
+                })
+                .to_string()
+                .into_bytes(),
+                ONE_YOCTO_NEAR,
+                GAS_FOR_NFT_TRANSFER,
+            ),
+        }
+    }
+
+    /// Removes the NFT from the inventory after it was sent out. Does nothing if it isn't recorded.
+    pub(crate) fn internal_nft_withdrawn(
+        &mut self,
+        nft_contract_id: &AccountId,
+        token_id: &TokenId,
+    ) {
+        let mut token_ids = match self.nfts.get(nft_contract_id) {
+            Some(token_ids) => token_ids,
+            None => return,
+        };
+        if !token_ids.remove(token_id) {
+            return;
+        }
+        if token_ids.is_empty() {
+            self.nfts.remove(nft_contract_id);
+        } else {
+            self.nfts.insert(nft_contract_id, &token_ids);
+        }
+    }
+}
+
+#[near_bindgen]
+impl NonFungibleTokenReceiver for Contract {
+    /// Accepts any NFT sent to the DAO. Only NFTs of contracts listed in the policy or already recorded
+    /// are added to the inventory, others are kept without being recorded.
+    fn nft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        previous_owner_id: AccountId,
+        token_id: TokenId,
+        msg: String,
+    ) -> PromiseOrValue<bool> {
+        let _ = msg;
+        let nft_contract_id = env::predecessor_account_id();
+        if self.nfts.get(&nft_contract_id).is_none()
+            && !self
+                .policy
+                .get()
+                .unwrap()
+                .to_policy()
+                .treasury_nft_contracts
+                .contains(&nft_contract_id)
+        {
+            log!(
+                "NFT contract {} is not recorded in the treasury",
+                nft_contract_id
+            );
+            return PromiseOrValue::Value(false);
+        }
+        let mut token_ids = self
+            .nfts
+            .get(&nft_contract_id)
+            .unwrap_or_else(|| UnorderedSet::new(StorageKeys::NftTokens(nft_contract_id.clone())));
+        if token_ids.insert(&token_id) {
+            self.nfts.insert(&nft_contract_id, &token_ids);
+        }
+        Event::NftDeposited {
+            nft_contract_id: &nft_contract_id,
+            token_id: &token_id,
+            sender_id: &sender_id,
+            previous_owner_id: &previous_owner_id,
+        }
+        .emit();
+        PromiseOrValue::Value(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, PromiseResult};
+    use near_sdk_sim::to_yocto;
+
+    use crate::test_utils::resolve_proposal;
+    use crate::views::NftOutput;
+
+    use super::*;
+
+    fn add_transfer(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+        msg: Option<String>,
+    ) -> u64 {
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(to_yocto("1"))
+            .build());
+        let id = contract.add_proposal(ProposalInput {
+            description: "ticket".to_string(),
+            kind: ProposalKind::TransferNft {
+                nft_contract_id: accounts(3),
+                token_id: "1".to_string(),
+                receiver_id: accounts(2),
+                msg,
+            },
+        });
+        testing_env!(context.attached_deposit(0).build());
+        contract.act_proposal(id, Action::VoteApprove, None);
+        assert_eq!(
+            contract.get_proposal(id).proposal.status,
+            ProposalStatus::Approved
+        );
+        id
+    }
+
+    #[test]
+    fn test_nft_inventory() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context
+            .current_account_id(accounts(0))
+            .predecessor_account_id(accounts(1))
+            .build());
+        let mut policy = VersionedPolicy::Default(vec![accounts(1)]).upgrade();
+        policy.to_policy_mut().treasury_nft_contracts = vec![accounts(3)];
+        let mut contract = Contract::new(Config::test_config(), policy);
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.nft_on_transfer(accounts(1), accounts(1), "1".to_string(), String::new());
+        contract.nft_on_transfer(accounts(1), accounts(1), "2".to_string(), String::new());
+        // A token that is already recorded isn't added twice.
+        contract.nft_on_transfer(accounts(1), accounts(1), "1".to_string(), String::new());
+        // NFTs of contracts that are not listed in the policy are not recorded.
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.nft_on_transfer(accounts(1), accounts(1), "3".to_string(), String::new());
+        assert_eq!(
+            contract.get_nfts(0, 10),
+            vec![NftOutput {
+                nft_contract_id: accounts(3),
+                token_ids: vec!["1".to_string(), "2".to_string()],
+            }]
+        );
+
+        // Receiver of `nft_transfer_call` returned the token, so the DAO still holds it.
+        let id = add_transfer(&mut context, &mut contract, Some("hi".to_string()));
+        resolve_proposal(
+            &context,
+            &mut contract,
+            id,
+            PromiseResult::Successful(b"false".to_vec()),
+        );
+        assert_eq!(contract.get_nft_tokens(accounts(3)).len(), 2);
+
+        let id = add_transfer(&mut context, &mut contract, None);
+        resolve_proposal(
+            &context,
+            &mut contract,
+            id,
+            PromiseResult::Successful(vec![]),
+        );
+        assert_eq!(contract.get_nft_tokens(accounts(3)), vec!["2".to_string()]);
+    }
+}
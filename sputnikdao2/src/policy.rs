@@ -6,8 +6,10 @@ use near_sdk::json_types::{U128, U64};
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::{env, AccountId, Balance};
 
+use crate::conviction::ConvictionPolicy;
 use crate::proposals::{PolicyParameters, Proposal, ProposalKind, ProposalStatus, Vote};
-use crate::types::Action;
+use crate::spending::SpendingLimit;
+use crate::types::{Action, OldAccountId};
 
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
 #[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
@@ -73,6 +75,51 @@ pub struct RolePermission {
     pub permissions: HashSet<String>,
     /// For each proposal kind, defines voting policy.
     pub vote_policy: HashMap<String, VotePolicy>,
+    /// Caps per token on transfers this role can decide with a lighter vote policy.
+    /// Overrides the policy's `spending_limits` for the same token.
+    #[serde(default)]
+    pub spending_limits: HashMap<OldAccountId, SpendingLimit>,
+}
+
+/// Role as it was stored before quorum kinds were introduced.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct RolePermissionV1 {
+    pub name: String,
+    pub kind: RoleKind,
+    pub permissions: HashSet<String>,
+    pub vote_policy: HashMap<String, VotePolicyV1>,
+}
+
+impl From<RolePermissionV1> for RolePermission {
+    fn from(role: RolePermissionV1) -> Self {
+        RolePermission {
+            name: role.name,
+            kind: role.kind,
+            permissions: role.permissions,
+            vote_policy: role
+                .vote_policy
+                .into_iter()
+                .map(|(label, vote_policy)| (label, vote_policy.into()))
+                .collect(),
+            spending_limits: HashMap::default(),
+        }
+    }
+}
+
+impl RolePermission {
+    /// Vote policy of this role for given proposal kind, under the most specific matching label.
+    pub fn vote_policy_for(&self, proposal_kind: &ProposalKind) -> Option<&VotePolicy> {
+        self.vote_policy
+            .iter()
+            .filter_map(|(label, vote_policy)| {
+                Some((proposal_kind.match_policy_label(label)?, label, vote_policy))
+            })
+            // Equally specific labels are ordered by name, to not depend on the map order.
+            .max_by(|(a, a_label, _), (b, b_label, _)| a.cmp(b).then(b_label.cmp(a_label)))
+            .map(|(_, _, vote_policy)| vote_policy)
+    }
 }
 
 pub struct UserInfo {
@@ -107,11 +154,107 @@ impl WeightOrRatio {
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq)]
 #[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
 #[serde(crate = "near_sdk::serde")]
+#[allow(clippy::enum_variant_names)]
 pub enum WeightKind {
     /// Using token amounts and total delegated at the moment.
     TokenWeight,
     /// Weight of the group role. Roles that don't have scoped group are not supported.
     RoleWeight,
+    /// Using integer square root of token amounts, measured against the sum of square roots of all delegations.
+    QuadraticTokenWeight,
+}
+
+/// Integer square root, rounded down.
+pub fn isqrt(n: Balance) -> Balance {
+    if n < 2 {
+        return n;
+    }
+    // Start from a power of two above the root, Newton's iterations then decrease to it.
+    let mut x: Balance = 1 << ((128 - n.leading_zeros()) / 2 + 1);
+    loop {
+        let y = (x + n / x) / 2;
+        if y >= x {
+            return x;
+        }
+        x = y;
+    }
+}
+
+/// Total delegated weight token weighted votes are measured against.
+#[derive(Clone, Copy, Default)]
+pub struct TotalSupply {
+    /// Total delegated amount.
+    pub amount: Balance,
+    /// Sum of square roots of all delegated amounts.
+    pub quadratic_amount: Balance,
+}
+
+/// Which votes count towards the quorum.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub enum QuorumKind {
+    /// Only votes for the winning decision count.
+    #[default]
+    Decision,
+    /// All approve, reject and abstain votes count.
+    /// Threshold is then measured on approve vs reject votes only.
+    Participation,
+}
+
+/// Rule combining decisions of several roles into the decision on the proposal.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub enum RoleCombination {
+    /// All of the roles must approve.
+    AllOf(Vec<String>),
+    /// Any one of the roles approving is enough.
+    AnyOf(Vec<String>),
+    /// At least `k` of the roles must approve.
+    KOfN { k: u32, roles: Vec<String> },
+}
+
+impl RoleCombination {
+    /// Returns named roles and how many of them must approve.
+    pub fn roles(&self) -> (&[String], usize) {
+        let (roles, required) = match self {
+            RoleCombination::AllOf(roles) => (roles, roles.len()),
+            RoleCombination::AnyOf(roles) => (roles, 1),
+            RoleCombination::KOfN { k, roles } => (roles, *k as usize),
+        };
+        assert!(
+            required > 0 && required <= roles.len(),
+            "ERR_INVALID_ROLE_COMBINATION"
+        );
+        (roles, required)
+    }
+
+    /// Combines statuses decided by each of the named roles, in the same order.
+    /// Approved once enough roles approve. Once that can't happen anymore, Removed if the roles
+    /// that removed alone made it impossible, Rejected otherwise.
+    pub fn combine(&self, statuses: &[Option<ProposalStatus>]) -> Option<ProposalStatus> {
+        let (roles, required) = self.roles();
+        let count = |status: ProposalStatus| {
+            statuses
+                .iter()
+                .filter(|decided| **decided == Some(status.clone()))
+                .count()
+        };
+        let approved = count(ProposalStatus::Approved);
+        let removed = count(ProposalStatus::Removed);
+        let rejected = count(ProposalStatus::Rejected);
+        let allowed_against = roles.len() - required;
+        if approved >= required {
+            Some(ProposalStatus::Approved)
+        } else if removed > allowed_against {
+            Some(ProposalStatus::Removed)
+        } else if removed + rejected > allowed_against {
+            Some(ProposalStatus::Rejected)
+        } else {
+            None
+        }
+    }
 }
 
 /// Defines configuration of the vote.
@@ -129,6 +272,12 @@ pub struct VotePolicy {
     pub quorum: U128,
     /// How many votes to pass this vote.
     pub threshold: WeightOrRatio,
+    /// Which votes count towards the quorum.
+    #[serde(default)]
+    pub quorum_kind: QuorumKind,
+    /// Roles that decide on the proposal together. If not set, the first role to decide wins.
+    #[serde(default)]
+    pub combination: Option<RoleCombination>,
 }
 
 impl Default for VotePolicy {
@@ -137,6 +286,106 @@ impl Default for VotePolicy {
             weight_kind: WeightKind::RoleWeight,
             quorum: U128(0),
             threshold: WeightOrRatio::Ratio(1, 2),
+            quorum_kind: QuorumKind::Decision,
+            combination: None,
+        }
+    }
+}
+
+impl VotePolicy {
+    /// Returns status decided by the given votes of the role, if any.
+    /// `total_weight` is the weight of the whole role.
+    fn decided_status(
+        &self,
+        vote_counts: &[Balance; 4],
+        total_weight: Balance,
+    ) -> Option<ProposalStatus> {
+        let approve = vote_counts[Vote::Approve as usize];
+        let reject = vote_counts[Vote::Reject as usize];
+        let threshold = self.threshold_weight(total_weight);
+        match self.quorum_kind {
+            QuorumKind::Decision => {
+                if approve >= threshold {
+                    return Some(ProposalStatus::Approved);
+                } else if reject >= threshold {
+                    return Some(ProposalStatus::Rejected);
+                }
+            }
+            QuorumKind::Participation => {
+                let participation = approve + reject + vote_counts[Vote::Abstain as usize];
+                if participation >= self.quorum.0 && approve + reject > 0 {
+                    let threshold = match self.threshold {
+                        WeightOrRatio::Ratio(..) => self.threshold.to_weight(approve + reject),
+                        WeightOrRatio::Weight(_) => self.threshold.to_weight(total_weight),
+                    };
+                    if approve >= threshold {
+                        return Some(ProposalStatus::Approved);
+                    } else if reject >= threshold {
+                        return Some(ProposalStatus::Rejected);
+                    }
+                }
+            }
+        }
+        if vote_counts[Vote::Remove as usize] >= threshold {
+            Some(ProposalStatus::Removed)
+        } else {
+            None
+        }
+    }
+
+    /// Returns status decided by the poll ballots of the role, if any.
+    /// `leader` is the leading option with its weight, `participation` is the weight of all ballots.
+    fn decided_poll_status(
+        &self,
+        leader: Option<(u32, Balance)>,
+        participation: Balance,
+        vote_counts: &[Balance; 4],
+        total_weight: Balance,
+    ) -> Option<ProposalStatus> {
+        let threshold = self.threshold_weight(total_weight);
+        let leader_threshold = match self.quorum_kind {
+            QuorumKind::Decision => Some(threshold),
+            QuorumKind::Participation if participation >= self.quorum.0 => {
+                Some(match self.threshold {
+                    WeightOrRatio::Ratio(..) => self.threshold.to_weight(participation),
+                    WeightOrRatio::Weight(_) => self.threshold.to_weight(total_weight),
+                })
+            }
+            QuorumKind::Participation => None,
+        };
+        match (leader, leader_threshold) {
+            (Some((_, weight)), Some(leader_threshold)) if weight >= leader_threshold => {
+                Some(ProposalStatus::Approved)
+            }
+            _ if vote_counts[Vote::Remove as usize] >= threshold => Some(ProposalStatus::Removed),
+            _ => None,
+        }
+    }
+
+    /// Weight required to decide the vote: the larger of quorum and threshold.
+    fn threshold_weight(&self, total_weight: Balance) -> Balance {
+        std::cmp::max(self.quorum.0, self.threshold.to_weight(total_weight))
+    }
+}
+
+/// Vote policy as it was stored before quorum kinds were introduced.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct VotePolicyV1 {
+    pub weight_kind: WeightKind,
+    pub quorum: U128,
+    pub threshold: WeightOrRatio,
+}
+
+impl From<VotePolicyV1> for VotePolicy {
+    fn from(vote_policy: VotePolicyV1) -> Self {
+        VotePolicy {
+            weight_kind: vote_policy.weight_kind,
+            quorum: vote_policy.quorum,
+            threshold: vote_policy.threshold,
+            quorum_kind: QuorumKind::Decision,
+            combination: None,
         }
     }
 }
@@ -158,15 +407,80 @@ pub struct Policy {
     pub bounty_bond: U128,
     /// Period in which giving up on bounty is not punished.
     pub bounty_forgiveness_period: U64,
+    /// Delay between approval and execution per proposal kind.
+    /// Approved proposals of these kinds are queued and can be vetoed until the delay passes.
+    /// Proposal kinds not listed here are executed right away.
+    #[serde(default)]
+    pub execution_delay: HashMap<String, U64>,
+    /// If voters can change or retract their vote while proposal is in progress.
+    #[serde(default)]
+    pub allow_vote_change: bool,
+    /// Parameters of conviction voting on `ConvictionFunding` proposals.
+    #[serde(default)]
+    pub conviction: ConvictionPolicy,
+    /// Caps per token on transfers, for roles that don't set their own.
+    #[serde(default)]
+    pub spending_limits: HashMap<OldAccountId, SpendingLimit>,
+    /// If receivers of token payouts that are not registered on the token get registered
+    /// with `storage_deposit` paid by the DAO.
+    #[serde(default)]
+    pub register_token_receivers: bool,
+    /// DAOs that can move their proposals here with `MoveToHub`.
+    #[serde(default)]
+    pub moved_proposal_sources: Vec<AccountId>,
+    /// Fungible tokens tracked in the treasury, in addition to the ones already tracked.
+    #[serde(default)]
+    pub treasury_tokens: Vec<AccountId>,
+    /// NFT contracts whose tokens are recorded in the inventory, in addition to the ones already recorded.
+    #[serde(default)]
+    pub treasury_nft_contracts: Vec<AccountId>,
+}
+
+/// Policy as it was stored before execution delays were introduced.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct PolicyV1 {
+    pub roles: Vec<RolePermissionV1>,
+    pub default_vote_policy: VotePolicyV1,
+    pub proposal_bond: U128,
+    pub proposal_period: U64,
+    pub bounty_bond: U128,
+    pub bounty_forgiveness_period: U64,
+}
+
+impl From<PolicyV1> for Policy {
+    fn from(policy: PolicyV1) -> Self {
+        Policy {
+            roles: policy.roles.into_iter().map(RolePermission::from).collect(),
+            default_vote_policy: policy.default_vote_policy.into(),
+            proposal_bond: policy.proposal_bond,
+            proposal_period: policy.proposal_period,
+            bounty_bond: policy.bounty_bond,
+            bounty_forgiveness_period: policy.bounty_forgiveness_period,
+            execution_delay: HashMap::default(),
+            allow_vote_change: false,
+            conviction: ConvictionPolicy::default(),
+            spending_limits: HashMap::default(),
+            register_token_receivers: false,
+            moved_proposal_sources: vec![],
+            treasury_tokens: vec![],
+            treasury_nft_contracts: vec![],
+        }
+    }
 }
 
 /// Versioned policy.
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
 #[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
 #[serde(crate = "near_sdk::serde", untagged)]
+#[allow(clippy::large_enum_variant)]
 pub enum VersionedPolicy {
     /// Default policy with given accounts as council.
     Default(Vec<AccountId>),
+    /// Policy stored by previous versions of the contract. Never accepted as input.
+    #[serde(skip_deserializing)]
+    V1(PolicyV1),
     Current(Policy),
 }
 
@@ -184,6 +498,7 @@ pub fn default_policy(council: Vec<AccountId>) -> Policy {
                 kind: RoleKind::Everyone,
                 permissions: vec!["*:AddProposal".to_string()].into_iter().collect(),
                 vote_policy: HashMap::default(),
+                spending_limits: HashMap::default(),
             },
             RolePermission {
                 name: "council".to_string(),
@@ -194,11 +509,14 @@ pub fn default_policy(council: Vec<AccountId>) -> Policy {
                     "*:VoteApprove".to_string(),
                     "*:VoteReject".to_string(),
                     "*:VoteRemove".to_string(),
+                    "*:VoteAbstain".to_string(),
+                    "*:CastBallot".to_string(),
                     "*:Finalize".to_string(),
                 ]
                 .into_iter()
                 .collect(),
                 vote_policy: HashMap::default(),
+                spending_limits: HashMap::default(),
             },
         ],
         default_vote_policy: VotePolicy::default(),
@@ -206,6 +524,14 @@ pub fn default_policy(council: Vec<AccountId>) -> Policy {
         proposal_period: U64::from(1_000_000_000 * 60 * 60 * 24 * 7),
         bounty_bond: U128(10u128.pow(24)),
         bounty_forgiveness_period: U64::from(1_000_000_000 * 60 * 60 * 24),
+        execution_delay: HashMap::default(),
+        allow_vote_change: false,
+        conviction: ConvictionPolicy::default(),
+        spending_limits: HashMap::default(),
+        register_token_receivers: false,
+        moved_proposal_sources: vec![],
+        treasury_tokens: vec![],
+        treasury_nft_contracts: vec![],
     }
 }
 
@@ -216,6 +542,7 @@ impl VersionedPolicy {
             VersionedPolicy::Default(accounts) => {
                 VersionedPolicy::Current(default_policy(accounts))
             }
+            VersionedPolicy::V1(policy) => VersionedPolicy::Current(policy.into()),
             VersionedPolicy::Current(policy) => VersionedPolicy::Current(policy),
         }
     }
@@ -223,6 +550,7 @@ impl VersionedPolicy {
     /// Return recent version of policy.
     pub fn to_policy(self) -> Policy {
         match self {
+            VersionedPolicy::V1(policy) => policy.into(),
             VersionedPolicy::Current(policy) => policy,
             _ => unimplemented!(),
         }
@@ -324,6 +652,11 @@ impl Policy {
         roles
     }
 
+    /// Checks if given user is member of the role with given name.
+    pub fn has_role(&self, user: &UserInfo, role: &String) -> bool {
+        matches!(self.internal_get_role(role), Some(role) if role.kind.match_user(user))
+    }
+
     /// Can given user execute given action on this proposal.
     /// Returns all roles that allow this action.
     pub fn can_execute_action(
@@ -337,14 +670,19 @@ impl Policy {
         let allowed_roles = roles
             .into_iter()
             .filter_map(|(role, permissions)| {
-                let allowed_role = permissions.contains(&format!(
-                    "{}:{}",
-                    proposal_kind.to_policy_label(),
-                    action.to_policy_label()
-                )) || permissions
-                    .contains(&format!("{}:*", proposal_kind.to_policy_label()))
-                    || permissions.contains(&format!("*:{}", action.to_policy_label()))
-                    || permissions.contains("*:*");
+                // Permissions are `<proposal_label>:<action>`, either part can be `*`.
+                let allowed_role =
+                    permissions
+                        .iter()
+                        .any(|permission| match permission.rsplit_once(':') {
+                            Some((kind_label, action_label)) => {
+                                (kind_label == "*"
+                                    || proposal_kind.match_policy_label(kind_label).is_some())
+                                    && (action_label == "*"
+                                        || action_label == action.to_policy_label())
+                            }
+                            None => false,
+                        });
                 allowed = allowed || allowed_role;
                 if allowed_role {
                     Some(role)
@@ -356,20 +694,43 @@ impl Policy {
         (allowed_roles, allowed)
     }
 
-    /// Returns if given proposal kind is token weighted.
-    pub fn is_token_weighted(&self, role: &String, proposal_kind_label: &String) -> bool {
+    /// Returns weight of the vote with given delegated amount, for given role and proposal kind.
+    pub fn vote_weight(
+        &self,
+        role: &String,
+        proposal_kind: &ProposalKind,
+        amount: Balance,
+    ) -> Balance {
         let role_info = self.internal_get_role(role).expect("ERR_ROLE_NOT_FOUND");
         match role_info
-            .vote_policy
-            .get(proposal_kind_label)
+            .vote_policy_for(proposal_kind)
             .unwrap_or(&self.default_vote_policy)
             .weight_kind
         {
-            WeightKind::TokenWeight => true,
-            _ => false,
+            WeightKind::TokenWeight => amount,
+            WeightKind::QuadraticTokenWeight => isqrt(amount),
+            WeightKind::RoleWeight => 1,
         }
     }
 
+    /// Returns true if votes on given proposal kind are weighted quadratically for any role.
+    pub fn uses_quadratic_weight(&self, proposal_kind: &ProposalKind) -> bool {
+        self.roles.iter().any(|role| {
+            role.vote_policy_for(proposal_kind)
+                .unwrap_or(&self.default_vote_policy)
+                .weight_kind
+                == WeightKind::QuadraticTokenWeight
+        })
+    }
+
+    /// Returns delay in nanoseconds between approval and execution of given proposal kind.
+    pub fn execution_delay(&self, proposal_kind: &ProposalKind) -> u64 {
+        self.execution_delay
+            .get(proposal_kind.to_policy_label())
+            .map(|delay| delay.0)
+            .unwrap_or_default()
+    }
+
     fn internal_get_role(&self, name: &String) -> Option<&RolePermission> {
         for role in self.roles.iter() {
             if role.name == *name {
@@ -385,7 +746,7 @@ impl Policy {
         &self,
         proposal: &Proposal,
         roles: Vec<String>,
-        total_supply: Balance,
+        total_supply: TotalSupply,
     ) -> ProposalStatus {
         assert!(
             matches!(
@@ -394,50 +755,95 @@ impl Policy {
             ),
             "ERR_PROPOSAL_NOT_IN_PROGRESS"
         );
-        if proposal.submission_time.0 + self.proposal_period.0 < env::block_timestamp() {
+        // Conviction funding doesn't expire, it stays open until conviction passes the threshold.
+        if !matches!(proposal.kind, ProposalKind::ConvictionFunding { .. })
+            && proposal.submission_time.0 + self.proposal_period.0 < env::block_timestamp()
+        {
             // Proposal expired.
             return ProposalStatus::Expired;
         };
+        if let Some(combination) = self.role_combination(&proposal.kind) {
+            // Decisions of all the named roles count, not only of the given ones.
+            let (combined_roles, _) = combination.roles();
+            let statuses: Vec<Option<ProposalStatus>> = combined_roles
+                .iter()
+                .map(|role| self.role_decided_status(proposal, role, total_supply))
+                .collect();
+            return combination
+                .combine(&statuses)
+                .unwrap_or_else(|| proposal.status.clone());
+        }
         for role in roles {
-            let role_info = self.internal_get_role(&role).expect("ERR_MISSING_ROLE");
-            let vote_policy = role_info
-                .vote_policy
-                .get(&proposal.kind.to_policy_label().to_string())
-                .unwrap_or(&self.default_vote_policy);
-            let total_weight = match &role_info.kind {
-                // Skip role that covers everyone as it doesn't provide a total size.
-                RoleKind::Everyone => continue,
-                RoleKind::Group(group) => {
-                    if vote_policy.weight_kind == WeightKind::RoleWeight {
-                        group.len() as Balance
-                    } else {
-                        total_supply
-                    }
-                }
-                RoleKind::Member(_) => total_supply,
-            };
-            let threshold = std::cmp::max(
-                vote_policy.quorum.0,
-                vote_policy.threshold.to_weight(total_weight),
-            );
-            // Check if there is anything voted above the threshold specified by policy for given role.
-            let vote_counts = proposal.vote_counts.get(&role).unwrap_or(&[0u128; 3]);
-            if vote_counts[Vote::Approve as usize] >= threshold {
-                return ProposalStatus::Approved;
-            } else if vote_counts[Vote::Reject as usize] >= threshold {
-                return ProposalStatus::Rejected;
-            } else if vote_counts[Vote::Remove as usize] >= threshold {
-                return ProposalStatus::Removed;
-            } else {
-                // continue to next role.
+            if let Some(status) = self.role_decided_status(proposal, &role, total_supply) {
+                return status;
             }
+            // continue to next role.
         }
         proposal.status.clone()
     }
+
+    /// Returns rule combining role decisions on given proposal kind: the first one set in the
+    /// roles' vote policies for this kind, otherwise the one of the default vote policy.
+    fn role_combination(&self, proposal_kind: &ProposalKind) -> Option<&RoleCombination> {
+        self.roles
+            .iter()
+            .find_map(|role| role.vote_policy_for(proposal_kind)?.combination.as_ref())
+            .or(self.default_vote_policy.combination.as_ref())
+    }
+
+    /// Returns winning option of the poll: leading option of the first role that approved it.
+    /// If the poll kind has a role combination, only the combined roles are considered.
+    pub fn poll_winner(
+        &self,
+        proposal: &Proposal,
+        roles: &[String],
+        total_supply: TotalSupply,
+    ) -> Option<u32> {
+        let roles = match self.role_combination(&proposal.kind) {
+            Some(combination) => combination.roles().0,
+            None => roles,
+        };
+        let role = roles.iter().find(|role| {
+            self.role_decided_status(proposal, role, total_supply) == Some(ProposalStatus::Approved)
+        })?;
+        proposal.poll_leader(role).map(|(option, _)| option)
+    }
+
+    /// Returns status decided by the votes of the given role, if any.
+    fn role_decided_status(
+        &self,
+        proposal: &Proposal,
+        role: &String,
+        total_supply: TotalSupply,
+    ) -> Option<ProposalStatus> {
+        let role_info = self.internal_get_role(role).expect("ERR_MISSING_ROLE");
+        let vote_policy = role_info
+            .vote_policy_for(&proposal.kind)
+            .unwrap_or(&self.default_vote_policy);
+        let total_weight = match (&role_info.kind, &vote_policy.weight_kind) {
+            // Skip role that covers everyone as it doesn't provide a total size.
+            (RoleKind::Everyone, _) => return None,
+            (RoleKind::Group(group), WeightKind::RoleWeight) => group.len() as Balance,
+            (_, WeightKind::QuadraticTokenWeight) => total_supply.quadratic_amount,
+            _ => total_supply.amount,
+        };
+        // Check if there is anything voted above the threshold specified by policy for given role.
+        let vote_counts = proposal.vote_counts.get(role).unwrap_or(&[0u128; 4]);
+        match proposal.kind {
+            ProposalKind::Poll { .. } => vote_policy.decided_poll_status(
+                proposal.poll_leader(role),
+                proposal.poll_participation(role),
+                vote_counts,
+                total_weight,
+            ),
+            _ => vote_policy.decided_status(vote_counts, total_weight),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use near_sdk::serde_json::{self, json};
     use near_sdk::test_utils::accounts;
 
     use super::*;
@@ -454,6 +860,210 @@ mod tests {
         assert_eq!(r2.to_weight(5), 5);
     }
 
+    #[test]
+    fn test_isqrt() {
+        assert_eq!(isqrt(0), 0);
+        assert_eq!(isqrt(1), 1);
+        assert_eq!(isqrt(15), 3);
+        assert_eq!(isqrt(16), 4);
+        assert_eq!(isqrt(10u128.pow(24)), 10u128.pow(12));
+        assert_eq!(isqrt(10u128.pow(24) - 1), 10u128.pow(12) - 1);
+        assert_eq!(isqrt(u128::MAX), u64::MAX as u128);
+    }
+
+    #[test]
+    fn test_quadratic_thresholds() {
+        let mut policy = default_policy(vec![accounts(0)]);
+        let whale = u128::MAX;
+        let root = isqrt(whale);
+        policy.default_vote_policy = VotePolicy {
+            weight_kind: WeightKind::QuadraticTokenWeight,
+            quorum: U128(root),
+            threshold: WeightOrRatio::Ratio(1, 4),
+            quorum_kind: QuorumKind::Decision,
+            combination: None,
+        };
+        assert_eq!(
+            policy.vote_weight(&"council".to_string(), &ProposalKind::Vote, whale),
+            u64::MAX as u128
+        );
+        let vote_policy = &policy.default_vote_policy;
+        // Quorum is above the ratio: vote reaching exactly the quorum passes, one less doesn't.
+        assert_eq!(
+            vote_policy.decided_status(&[root, 0, 0, 0], 3 * root),
+            Some(ProposalStatus::Approved)
+        );
+        assert_eq!(
+            vote_policy.decided_status(&[root - 1, 0, 0, 0], 3 * root),
+            None
+        );
+        // Ratio is above the quorum and has to be exceeded.
+        assert_eq!(vote_policy.decided_status(&[root, 0, 0, 0], 4 * root), None);
+        assert_eq!(
+            vote_policy.decided_status(&[root + 1, 0, 0, 0], 4 * root),
+            Some(ProposalStatus::Approved)
+        );
+    }
+
+    #[test]
+    fn test_participation_quorum() {
+        let vote_policy = VotePolicy {
+            weight_kind: WeightKind::TokenWeight,
+            quorum: U128(100),
+            threshold: WeightOrRatio::Ratio(1, 2),
+            quorum_kind: QuorumKind::Participation,
+            combination: None,
+        };
+        // Not enough participation yet.
+        assert_eq!(vote_policy.decided_status(&[30, 10, 0, 50], 1_000), None);
+        // Abstain votes complete the quorum, approve wins over reject.
+        assert_eq!(
+            vote_policy.decided_status(&[30, 10, 0, 60], 1_000),
+            Some(ProposalStatus::Approved)
+        );
+        assert_eq!(
+            vote_policy.decided_status(&[10, 30, 0, 60], 1_000),
+            Some(ProposalStatus::Rejected)
+        );
+        // Tie or only abstains decide nothing.
+        assert_eq!(vote_policy.decided_status(&[20, 20, 0, 60], 1_000), None);
+        assert_eq!(vote_policy.decided_status(&[0, 0, 0, 200], 1_000), None);
+        // Same votes don't pass when quorum counts only the winning decision.
+        let vote_policy = VotePolicy {
+            quorum_kind: QuorumKind::Decision,
+            ..vote_policy
+        };
+        assert_eq!(vote_policy.decided_status(&[30, 10, 0, 60], 1_000), None);
+    }
+
+    #[test]
+    fn test_role_combination() {
+        let roles = vec![
+            "council".to_string(),
+            "holders".to_string(),
+            "experts".to_string(),
+        ];
+        let all_of = RoleCombination::AllOf(roles.clone());
+        let any_of = RoleCombination::AnyOf(roles.clone());
+        let two_of_three = RoleCombination::KOfN { k: 2, roles };
+        let approved = Some(ProposalStatus::Approved);
+        let rejected = Some(ProposalStatus::Rejected);
+        let removed = Some(ProposalStatus::Removed);
+
+        let statuses = [approved.clone(), approved.clone(), None];
+        assert_eq!(all_of.combine(&statuses), None);
+        assert_eq!(any_of.combine(&statuses), approved);
+        assert_eq!(two_of_three.combine(&statuses), approved);
+
+        // One chamber rejecting is enough to reject when all must approve.
+        let statuses = [approved.clone(), rejected.clone(), None];
+        assert_eq!(all_of.combine(&statuses), rejected);
+        assert_eq!(any_of.combine(&statuses), approved);
+        assert_eq!(two_of_three.combine(&statuses), None);
+
+        let statuses = [removed.clone(), rejected.clone(), None];
+        assert_eq!(all_of.combine(&statuses), removed);
+        assert_eq!(any_of.combine(&statuses), None);
+        assert_eq!(two_of_three.combine(&statuses), rejected);
+
+        let statuses = [removed.clone(), removed.clone(), rejected.clone()];
+        assert_eq!(any_of.combine(&statuses), rejected);
+        assert_eq!(two_of_three.combine(&statuses), removed);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_ROLE_COMBINATION")]
+    fn test_invalid_role_combination() {
+        RoleCombination::KOfN {
+            k: 3,
+            roles: vec!["council".to_string(), "holders".to_string()],
+        }
+        .combine(&[None, None]);
+    }
+
+    fn function_call(receiver_id: &str, methods: &[&str]) -> ProposalKind {
+        let actions: Vec<_> = methods
+            .iter()
+            .map(|method_name| {
+                json!({"method_name": method_name, "args": "", "deposit": "0", "gas": "0"})
+            })
+            .collect();
+        serde_json::from_value(json!({
+            "FunctionCall": {"receiver_id": receiver_id, "actions": actions}
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_scoped_call_permissions() {
+        let mut policy = default_policy(vec![accounts(0)]);
+        policy.add_or_update_role(&RolePermission {
+            name: "dev".to_string(),
+            kind: RoleKind::Group(vec![accounts(1)].into_iter().collect()),
+            permissions: vec![
+                "call@ref-finance.near/swap:*".to_string(),
+                "call@*.ref-finance.near/ft_*:VoteReject".to_string(),
+            ]
+            .into_iter()
+            .collect(),
+            vote_policy: HashMap::default(),
+            spending_limits: HashMap::default(),
+        });
+        let can = |account_id: AccountId, kind: &ProposalKind, action: Action| {
+            let user = UserInfo {
+                account_id,
+                amount: 0,
+            };
+            policy.can_execute_action(user, kind, &action).1
+        };
+        let swap = function_call("ref-finance.near", &["swap"]);
+        assert!(can(accounts(1), &swap, Action::VoteApprove));
+        assert!(can(accounts(1), &swap, Action::VoteReject));
+        // Every method of the call must be allowed.
+        let swap_and_withdraw = function_call("ref-finance.near", &["swap", "withdraw"]);
+        assert!(!can(accounts(1), &swap_and_withdraw, Action::VoteApprove));
+        assert!(!can(
+            accounts(1),
+            &function_call("other.near", &["swap"]),
+            Action::VoteApprove
+        ));
+        // Wildcards match any part of receiver and method.
+        let transfer = function_call("v2.ref-finance.near", &["ft_transfer", "ft_transfer_call"]);
+        assert!(can(accounts(1), &transfer, Action::VoteReject));
+        assert!(!can(accounts(1), &transfer, Action::VoteApprove));
+        assert!(!can(
+            accounts(1),
+            &function_call("ref-finance.near", &["ft_transfer"]),
+            Action::VoteReject
+        ));
+        // Council keeps general `call` permissions via `*`.
+        assert!(can(accounts(0), &swap_and_withdraw, Action::VoteApprove));
+        assert!(!can(accounts(1), &ProposalKind::Vote, Action::VoteApprove));
+    }
+
+    #[test]
+    fn test_scoped_call_vote_policy() {
+        let mut role = default_policy(vec![accounts(0)]).roles[1].clone();
+        let vote_policy = |quorum| VotePolicy {
+            quorum: U128(quorum),
+            ..VotePolicy::default()
+        };
+        role.vote_policy.insert("call".to_string(), vote_policy(1));
+        role.vote_policy
+            .insert("call@ref-finance.near/*".to_string(), vote_policy(2));
+        role.vote_policy
+            .insert("call@ref-finance.near/swap".to_string(), vote_policy(3));
+        let quorum = |kind: &ProposalKind| role.vote_policy_for(kind).unwrap().quorum.0;
+        assert_eq!(quorum(&function_call("ref-finance.near", &["swap"])), 3);
+        assert_eq!(quorum(&function_call("ref-finance.near", &["withdraw"])), 2);
+        assert_eq!(
+            quorum(&function_call("ref-finance.near", &["swap", "withdraw"])),
+            2
+        );
+        assert_eq!(quorum(&function_call("other.near", &["swap"])), 1);
+        assert!(role.vote_policy_for(&ProposalKind::Vote).is_none());
+    }
+
     #[test]
     fn test_add_role() {
         let council = vec![accounts(0), accounts(1)];
@@ -471,6 +1081,7 @@ mod tests {
             kind: kind.clone(),
             permissions: permissions.clone(),
             vote_policy: vote_policy.clone(),
+            spending_limits: HashMap::default(),
         };
         assert_eq!(2, policy.roles.len());
         policy.add_or_update_role(&new_role);
@@ -498,6 +1109,8 @@ mod tests {
             "*:VoteApprove".to_string(),
             "*:VoteReject".to_string(),
             "*:VoteRemove".to_string(),
+            "*:VoteAbstain".to_string(),
+            "*:CastBallot".to_string(),
             "*:Finalize".to_string(),
         ]
         .into_iter()
@@ -520,6 +1133,7 @@ mod tests {
             kind: kind.clone(),
             permissions: permissions.clone(),
             vote_policy: vote_policy.clone(),
+            spending_limits: HashMap::default(),
         };
         assert_eq!(2, policy.roles.len());
         policy.add_or_update_role(&updated_role);
@@ -570,6 +1184,8 @@ mod tests {
             weight_kind: WeightKind::TokenWeight,
             quorum: U128(100),
             threshold: WeightOrRatio::Ratio(1, 4),
+            quorum_kind: QuorumKind::Decision,
+            combination: None,
         };
         policy.update_default_vote_policy(&new_default_vote_policy);
         assert_eq!(
@@ -0,0 +1,239 @@
+//! Multi-option polls: ballots, per-option tallies and winner selection.
+
+use std::collections::HashMap;
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{AccountId, Balance};
+
+/// Maximum number of options in a poll. Bounds the gas of instant-runoff tallying.
+pub const MAX_POLL_OPTIONS: usize = 16;
+
+/// How ballots of the poll are cast and counted.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub enum PollTally {
+    /// Ballot picks a single option, the option with most weight wins.
+    Plurality,
+    /// Ballot approves any number of options, the option with most weight wins.
+    Approval,
+    /// Ballot ranks options by preference. Options with the least weight are eliminated one by one,
+    /// passing their ballots to the next preference, until one option has the majority.
+    InstantRunoff,
+}
+
+impl PollTally {
+    /// Checks that the ballot is valid for this kind of poll with `num_options` options.
+    pub fn assert_valid_ballot(&self, ballot: &[u32], num_options: usize) {
+        assert!(!ballot.is_empty(), "ERR_EMPTY_BALLOT");
+        assert!(
+            *self != PollTally::Plurality || ballot.len() == 1,
+            "ERR_SINGLE_CHOICE_BALLOT"
+        );
+        let mut chosen = vec![false; num_options];
+        for &option in ballot {
+            let option = option as usize;
+            assert!(option < num_options, "ERR_INVALID_POLL_OPTION");
+            assert!(!chosen[option], "ERR_DUPLICATE_POLL_OPTION");
+            chosen[option] = true;
+        }
+    }
+
+    /// Options that get the weight of the ballot in stored tallies.
+    /// Ranked ballots only count for their first preference.
+    fn counted_options<'a>(&self, ballot: &'a [u32]) -> &'a [u32] {
+        match self {
+            PollTally::InstantRunoff => &ballot[..1],
+            _ => ballot,
+        }
+    }
+}
+
+/// Ballots and tallies of a poll proposal.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Default)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct PollVotes {
+    /// Ballot of each voter: indices of chosen options, in order of preference for ranked polls.
+    pub ballots: HashMap<AccountId, Vec<u32>>,
+    /// Weight of ballots per role per option.
+    pub tallies: HashMap<String, Vec<Balance>>,
+    /// Winning option, set when the poll is approved.
+    pub winner: Option<u32>,
+}
+
+impl PollVotes {
+    /// Adds `amount` of weight of the ballot to the tallies of the given role.
+    pub fn add_to_tallies(
+        &mut self,
+        tally: &PollTally,
+        num_options: usize,
+        role: &str,
+        ballot: &[u32],
+        amount: Balance,
+    ) {
+        let tallies = self
+            .tallies
+            .entry(role.to_string())
+            .or_insert_with(|| vec![0; num_options]);
+        for &option in tally.counted_options(ballot) {
+            tallies[option as usize] += amount;
+        }
+    }
+
+    /// Takes back `amount` of weight of the ballot from the tallies of the given role.
+    pub fn remove_from_tallies(
+        &mut self,
+        tally: &PollTally,
+        role: &str,
+        ballot: &[u32],
+        amount: Balance,
+    ) {
+        if let Some(tallies) = self.tallies.get_mut(role) {
+            for &option in tally.counted_options(ballot) {
+                tallies[option as usize] -= amount;
+            }
+        }
+    }
+
+    /// Returns leading option of the given role and its weight, if there is a single leader.
+    /// `vote_weights` are the weights ballots were cast with per voter per role.
+    pub fn leader(
+        &self,
+        tally: &PollTally,
+        num_options: usize,
+        role: &str,
+        vote_weights: &HashMap<AccountId, HashMap<String, Balance>>,
+    ) -> Option<(u32, Balance)> {
+        match tally {
+            PollTally::InstantRunoff => self.runoff_leader(num_options, role, vote_weights),
+            _ => single_max(self.tallies.get(role)?, &vec![true; num_options]),
+        }
+    }
+
+    /// Runs instant-runoff rounds over the ballots of the given role.
+    /// Returns the option that got the majority of continuing ballots and its final weight.
+    fn runoff_leader(
+        &self,
+        num_options: usize,
+        role: &str,
+        vote_weights: &HashMap<AccountId, HashMap<String, Balance>>,
+    ) -> Option<(u32, Balance)> {
+        let ballots: Vec<(&Vec<u32>, Balance)> = self
+            .ballots
+            .iter()
+            .filter_map(|(account_id, ballot)| {
+                let weight = *vote_weights.get(account_id)?.get(role)?;
+                Some((ballot, weight))
+            })
+            .collect();
+        let mut active = vec![true; num_options];
+        loop {
+            let mut counts = vec![0; num_options];
+            for (ballot, weight) in ballots.iter() {
+                if let Some(&option) = ballot.iter().find(|&&option| active[option as usize]) {
+                    counts[option as usize] += weight;
+                }
+            }
+            let continuing: Balance = counts.iter().sum();
+            if continuing == 0 {
+                return None;
+            }
+            if let Some((option, weight)) = single_max(&counts, &active) {
+                if weight > continuing - weight {
+                    return Some((option, weight));
+                }
+            }
+            // Eliminate the weakest option, on tie the one listed last.
+            let weakest = (0..num_options)
+                .filter(|&option| active[option])
+                .min_by_key(|&option| (counts[option], std::cmp::Reverse(option)))
+                .expect("INTERNAL_FAIL");
+            active[weakest] = false;
+            if !active.iter().any(|&is_active| is_active) {
+                return None;
+            }
+        }
+    }
+}
+
+/// Returns the active option with the most weight, if it is non-zero and not tied.
+fn single_max(counts: &[Balance], active: &[bool]) -> Option<(u32, Balance)> {
+    let mut leader: Option<(u32, Balance)> = None;
+    let mut tied = false;
+    for (option, &weight) in counts.iter().enumerate() {
+        if !active[option] || weight == 0 {
+            continue;
+        }
+        match leader {
+            Some((_, max)) if weight < max => {}
+            Some((_, max)) if weight == max => tied = true,
+            _ => {
+                leader = Some((option as u32, weight));
+                tied = false;
+            }
+        }
+    }
+    if tied {
+        None
+    } else {
+        leader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::test_utils::accounts;
+
+    use super::*;
+
+    fn cast(
+        poll: &mut PollVotes,
+        vote_weights: &mut HashMap<AccountId, HashMap<String, Balance>>,
+        account_id: AccountId,
+        ballot: Vec<u32>,
+        weight: Balance,
+    ) {
+        poll.add_to_tallies(&PollTally::InstantRunoff, 3, "all", &ballot, weight);
+        poll.ballots.insert(account_id.clone(), ballot);
+        vote_weights.insert(
+            account_id,
+            vec![("all".to_string(), weight)].into_iter().collect(),
+        );
+    }
+
+    #[test]
+    fn test_single_max() {
+        assert_eq!(single_max(&[1, 5, 3], &[true; 3]), Some((1, 5)));
+        assert_eq!(single_max(&[5, 5, 3], &[true; 3]), None);
+        assert_eq!(single_max(&[5, 5, 3], &[false, true, true]), Some((1, 5)));
+        assert_eq!(single_max(&[0, 0], &[true; 2]), None);
+    }
+
+    #[test]
+    fn test_instant_runoff() {
+        let mut poll = PollVotes::default();
+        let mut vote_weights = HashMap::default();
+        cast(&mut poll, &mut vote_weights, accounts(0), vec![0, 1], 4);
+        cast(&mut poll, &mut vote_weights, accounts(1), vec![1, 0], 3);
+        cast(&mut poll, &mut vote_weights, accounts(2), vec![2, 1], 2);
+        // First preferences: 4 / 3 / 2, so plurality would pick option 0.
+        assert_eq!(poll.tallies["all"], vec![4, 3, 2]);
+        // Option 2 is eliminated and its ballot goes to option 1, which then has the majority.
+        assert_eq!(
+            poll.leader(&PollTally::InstantRunoff, 3, "all", &vote_weights),
+            Some((1, 5))
+        );
+        assert_eq!(
+            poll.leader(&PollTally::Plurality, 3, "all", &vote_weights),
+            Some((0, 4))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_DUPLICATE_POLL_OPTION")]
+    fn test_duplicate_ballot_option() {
+        PollTally::Approval.assert_valid_ballot(&[1, 1], 3);
+    }
+}
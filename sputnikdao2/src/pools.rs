@@ -0,0 +1,209 @@
+//! Staking of treasury $NEAR with validator staking pools.
+//! Deposits are tracked per pool, as staked $NEAR is not part of the DAO's balance until withdrawn.
+
+use near_sdk::serde_json::json;
+
+use crate::types::GAS_FOR_STAKING_POOL;
+use crate::*;
+
+/// $NEAR the DAO deposited with a staking pool.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct PoolDeposit {
+    /// Staked amount.
+    pub staked: U128,
+    /// Unstaked amount that is not withdrawn yet.
+    pub unstaked: U128,
+}
+
+impl PoolDeposit {
+    /// Total amount held by the pool.
+    pub fn total(&self) -> Balance {
+        self.staked.0 + self.unstaked.0
+    }
+}
+
+impl Contract {
+    /// Calls `method_name` on the staking pool, attaching `deposit`.
+    pub(crate) fn internal_call_pool(
+        &self,
+        pool_id: &AccountId,
+        method_name: &str,
+        amount: Option<U128>,
+        deposit: Balance,
+    ) -> Promise {
+        let args = match amount {
+            Some(amount) => json!({ "amount": amount }),
+            None => json!({}),
+        };
+        Promise::new(pool_id.clone()).function_call(
+            method_name.to_string(),
+            args.to_string().into_bytes(),
+            deposit,
+            GAS_FOR_STAKING_POOL,
+        )
+    }
+
+    /// Updates the deposit with the pool after the staking proposal was executed.
+    pub(crate) fn internal_update_pool_deposit(&mut self, kind: &ProposalKind) {
+        let (pool_id, mut deposit) = match kind {
+            ProposalKind::StakeNear { pool_id, .. }
+            | ProposalKind::UnstakeNear { pool_id, .. }
+            | ProposalKind::WithdrawStakedNear { pool_id, .. } => {
+                let deposit = self.staking_pools.get(pool_id).unwrap_or(PoolDeposit {
+                    staked: U128(0),
+                    unstaked: U128(0),
+                });
+                (pool_id, deposit)
+            }
+            _ => return,
+        };
+        match kind {
+            ProposalKind::StakeNear { amount, .. } => {
+                deposit.staked = U128(deposit.staked.0 + amount.0);
+            }
+            // Rewards can be unstaked and withdrawn as well, so amounts may exceed the deposit.
+            ProposalKind::UnstakeNear { amount, .. } => {
+                deposit.staked = U128(deposit.staked.0.saturating_sub(amount.0));
+                deposit.unstaked = U128(deposit.unstaked.0 + amount.0);
+            }
+            ProposalKind::WithdrawStakedNear { amount, .. } => {
+                deposit.unstaked = U128(deposit.unstaked.0.saturating_sub(amount.0));
+            }
+            _ => unreachable!(),
+        }
+        if deposit.total() == 0 {
+            self.staking_pools.remove(pool_id);
+        } else {
+            self.staking_pools.insert(pool_id, &deposit);
+        }
+    }
+
+    /// Total $NEAR deposited with staking pools.
+    pub(crate) fn internal_staked_amount(&self) -> Balance {
+        self.staking_pools
+            .values()
+            .map(|deposit| deposit.total())
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::json_types::U64;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, PromiseResult};
+    use near_sdk_sim::to_yocto;
+
+    use crate::test_utils::{execute, resolve_proposal};
+    use crate::views::StakingPoolOutput;
+
+    use super::*;
+
+    fn execute_resolved(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+        kind: ProposalKind,
+    ) {
+        let id = execute(context, contract, kind);
+        resolve_proposal(context, contract, id, PromiseResult::Successful(vec![]));
+    }
+
+    #[test]
+    fn test_staking_pools() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context
+            .current_account_id(accounts(0))
+            .predecessor_account_id(accounts(1))
+            .account_balance(to_yocto("1000"))
+            .build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1)]),
+        );
+        let pool_id = accounts(3);
+        let amount = |amount: &str| U128(to_yocto(amount));
+
+        execute_resolved(
+            &mut context,
+            &mut contract,
+            ProposalKind::StakeNear {
+                pool_id: pool_id.clone(),
+                amount: amount("100"),
+            },
+        );
+        execute_resolved(
+            &mut context,
+            &mut contract,
+            ProposalKind::UnstakeNear {
+                pool_id: pool_id.clone(),
+                amount: amount("40"),
+            },
+        );
+        assert_eq!(
+            contract.get_staking_pools(0, 10),
+            vec![StakingPoolOutput {
+                pool_id: pool_id.clone(),
+                deposit: PoolDeposit {
+                    staked: amount("60"),
+                    unstaked: amount("40"),
+                },
+            }]
+        );
+        assert_eq!(contract.get_treasury(0, 1)[0].staked, amount("100"));
+
+        // Withdrawing rewards on top of the deposit clears the unstaked amount.
+        execute_resolved(
+            &mut context,
+            &mut contract,
+            ProposalKind::UnstakeNear {
+                pool_id: pool_id.clone(),
+                amount: amount("61"),
+            },
+        );
+        execute_resolved(
+            &mut context,
+            &mut contract,
+            ProposalKind::WithdrawStakedNear {
+                pool_id,
+                amount: amount("101"),
+            },
+        );
+        assert!(contract.get_staking_pools(0, 10).is_empty());
+        assert_eq!(contract.get_treasury(0, 1)[0].staked, U128(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ENOUGH_FUNDS")]
+    fn test_stake_reserved_funds() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context
+            .current_account_id(accounts(0))
+            .predecessor_account_id(accounts(1))
+            .account_balance(to_yocto("1000"))
+            .build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1)]),
+        );
+        contract.internal_add_bounty(&Bounty {
+            description: "test".to_string(),
+            token: String::from(OLD_BASE_TOKEN),
+            amount: U128(to_yocto("900")),
+            times: 1,
+            max_deadline: U64(0),
+            milestones: vec![],
+            assignment: None,
+            paused: false,
+        });
+        execute(
+            &mut context,
+            &mut contract,
+            ProposalKind::StakeNear {
+                pool_id: accounts(3),
+                amount: U128(to_yocto("200")),
+            },
+        );
+    }
+}
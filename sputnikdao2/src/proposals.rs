@@ -1,14 +1,18 @@
 use std::collections::HashMap;
 
-use near_contract_standards::fungible_token::core_impl::ext_fungible_token;
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::json_types::{Base64VecU8, U128, U64};
+use near_sdk::serde_json::{self, json};
 use near_sdk::{log, AccountId, Balance, Gas, PromiseOrValue};
 
-use crate::policy::UserInfo;
+use crate::bulk::read_bulk_transfer;
+use crate::events::Event;
+use crate::policy::{RolePermissionV1, UserInfo, VotePolicyV1};
+use crate::poll::MAX_POLL_OPTIONS;
+use crate::treasury::internal_ft_transfer;
 use crate::types::{
-    convert_old_to_new_token, Action, Config, OldAccountId, GAS_FOR_FT_TRANSFER, OLD_BASE_TOKEN,
-    ONE_YOCTO_NEAR,
+    convert_old_to_new_token, Action, Config, OldAccountId, GAS_FOR_MOVE_CALLBACK,
+    GAS_FOR_MOVE_PROPOSAL, GAS_FOR_PROPOSAL_CALLBACK, OLD_BASE_TOKEN,
 };
 use crate::upgrade::{upgrade_remote, upgrade_using_factory};
 use crate::*;
@@ -27,10 +31,22 @@ pub enum ProposalStatus {
     Removed,
     /// Expired after period of time.
     Expired,
-    /// If proposal was moved to Hub or somewhere else.
+    /// If proposal was moved to Hub or somewhere else. See `moved_to` for the new location.
     Moved,
     /// If proposal has failed when finalizing. Allowed to re-finalize again to either expire or approved.
     Failed,
+    /// Approved, but waits for the execution delay to pass. Can be vetoed until then.
+    Queued,
+}
+
+/// Reference to a proposal in another DAO.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ProposalLink {
+    /// Account of the DAO.
+    pub dao_id: AccountId,
+    /// Id of the proposal in that DAO.
+    pub proposal_id: u64,
 }
 
 /// Function call arguments.
@@ -96,10 +112,11 @@ pub enum ProposalKind {
     SetStakingContract { staking_id: AccountId },
     /// Add new bounty.
     AddBounty { bounty: Bounty },
-    /// Indicates that given bounty is done by given user.
+    /// Indicates that given bounty (or given milestone of it) is done by given user.
     BountyDone {
         bounty_id: u64,
         receiver_id: AccountId,
+        milestone: Option<u32>,
     },
     /// Just a signaling vote, with no execution.
     Vote,
@@ -113,6 +130,220 @@ pub enum ProposalKind {
     ChangePolicyUpdateDefaultVotePolicy { vote_policy: VotePolicy },
     /// Update the parameters from the policy. This is short cut to updating the whole policy.
     ChangePolicyUpdateParameters { parameters: PolicyParameters },
+    /// Poll between given options, with no execution. Voted with `CastBallot` action.
+    Poll {
+        options: Vec<String>,
+        tally: PollTally,
+    },
+    /// Funds `receiver_id` with given amount once conviction of stakes behind it passes the threshold.
+    /// Voted with `StakeConviction` and `UnstakeConviction` actions, doesn't expire.
+    ConvictionFunding {
+        /// Can be "" for $NEAR.
+        token_id: OldAccountId,
+        receiver_id: AccountId,
+        amount: U128,
+    },
+    /// Transfers NFT `token_id` of `nft_contract_id` from this DAO to `receiver_id`.
+    /// If `msg` is not None, calls `nft_transfer_call` with given `msg`.
+    /// `memo` is the `description` of the proposal.
+    TransferNft {
+        nft_contract_id: AccountId,
+        token_id: TokenId,
+        receiver_id: AccountId,
+        msg: Option<String>,
+    },
+    /// Stakes given amount of $NEAR with the staking pool via `deposit_and_stake`.
+    StakeNear { pool_id: AccountId, amount: U128 },
+    /// Unstakes given amount from the staking pool. It can be withdrawn after the pool's unstaking period.
+    UnstakeNear { pool_id: AccountId, amount: U128 },
+    /// Withdraws given amount of unstaked $NEAR from the staking pool back to the DAO.
+    WithdrawStakedNear { pool_id: AccountId, amount: U128 },
+    /// Pays out the rows of the blob with given hash, a JSON list of `receiver_id`, `token_id` and `amount`.
+    /// Rows are sent in batches that fit into the attached gas, see `bulk_transfer_continue`.
+    BulkTransfer { hash: Base58CryptoHash },
+    /// Streams `amount_per_period` of `token_id` to `receiver_id` for every `period` (in nanoseconds)
+    /// between `start` and `end`. Nothing vests before `cliff`. The receiver claims with `stream_claim`.
+    CreatePaymentStream {
+        token_id: OldAccountId,
+        receiver_id: AccountId,
+        amount_per_period: U128,
+        period: U64,
+        start: U64,
+        end: U64,
+        cliff: U64,
+    },
+    /// Cancels the payment stream created by proposal `stream_id`.
+    /// What has vested until now can still be claimed.
+    CancelPaymentStream { stream_id: u64 },
+    /// Grants `amount` of `token_id` to `beneficiary_id`, vesting as given by `vesting`.
+    /// The beneficiary claims with `vesting_claim`.
+    CreateVestingSchedule {
+        token_id: OldAccountId,
+        beneficiary_id: AccountId,
+        amount: U128,
+        vesting: Vesting,
+    },
+    /// Revokes the vesting schedule created by proposal `schedule_id`.
+    /// Unvested amount returns to the treasury, what has vested until now can still be claimed.
+    RevokeVestingSchedule { schedule_id: u64 },
+    /// Swaps tokens of the treasury on Ref Exchange and withdraws the proceeds back to the DAO.
+    /// The proposal fails if the swap doesn't return at least `min_amount_out`.
+    Swap { swap: Swap },
+    /// Approves the application of `account_id` for the bounty that requires assignment.
+    /// If the proposal is rejected or removed, the application is rejected and its bond refunded.
+    ApproveBountyClaim {
+        bounty_id: u64,
+        account_id: AccountId,
+    },
+    /// Changes amount, max deadline or pauses new claims of the bounty.
+    UpdateBounty {
+        bounty_id: u64,
+        update: BountyUpdate,
+    },
+    /// Removes the bounty and releases its funds.
+    /// Claims in progress are removed with their bonds returned.
+    RemoveBounty { bounty_id: u64 },
+}
+
+/// Kinds of proposals as they were stored before quorum kinds were introduced.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Clone, Debug))]
+#[serde(crate = "near_sdk::serde")]
+#[allow(clippy::large_enum_variant)]
+pub enum ProposalKindV1 {
+    ChangeConfig {
+        config: Config,
+    },
+    ChangePolicy {
+        policy: VersionedPolicy,
+    },
+    AddMemberToRole {
+        member_id: AccountId,
+        role: String,
+    },
+    RemoveMemberFromRole {
+        member_id: AccountId,
+        role: String,
+    },
+    FunctionCall {
+        receiver_id: AccountId,
+        actions: Vec<ActionCall>,
+    },
+    UpgradeSelf {
+        hash: Base58CryptoHash,
+    },
+    UpgradeRemote {
+        receiver_id: AccountId,
+        method_name: String,
+        hash: Base58CryptoHash,
+    },
+    Transfer {
+        token_id: OldAccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        msg: Option<String>,
+    },
+    SetStakingContract {
+        staking_id: AccountId,
+    },
+    AddBounty {
+        bounty: BountyV1,
+    },
+    BountyDone {
+        bounty_id: u64,
+        receiver_id: AccountId,
+    },
+    Vote,
+    FactoryInfoUpdate {
+        factory_info: FactoryInfo,
+    },
+    ChangePolicyAddOrUpdateRole {
+        role: RolePermissionV1,
+    },
+    ChangePolicyRemoveRole {
+        role: String,
+    },
+    ChangePolicyUpdateDefaultVotePolicy {
+        vote_policy: VotePolicyV1,
+    },
+    ChangePolicyUpdateParameters {
+        parameters: PolicyParameters,
+    },
+}
+
+impl From<ProposalKindV1> for ProposalKind {
+    fn from(kind: ProposalKindV1) -> Self {
+        match kind {
+            ProposalKindV1::ChangeConfig { config } => ProposalKind::ChangeConfig { config },
+            ProposalKindV1::ChangePolicy { policy } => ProposalKind::ChangePolicy { policy },
+            ProposalKindV1::AddMemberToRole { member_id, role } => {
+                ProposalKind::AddMemberToRole { member_id, role }
+            }
+            ProposalKindV1::RemoveMemberFromRole { member_id, role } => {
+                ProposalKind::RemoveMemberFromRole { member_id, role }
+            }
+            ProposalKindV1::FunctionCall {
+                receiver_id,
+                actions,
+            } => ProposalKind::FunctionCall {
+                receiver_id,
+                actions,
+            },
+            ProposalKindV1::UpgradeSelf { hash } => ProposalKind::UpgradeSelf { hash },
+            ProposalKindV1::UpgradeRemote {
+                receiver_id,
+                method_name,
+                hash,
+            } => ProposalKind::UpgradeRemote {
+                receiver_id,
+                method_name,
+                hash,
+            },
+            ProposalKindV1::Transfer {
+                token_id,
+                receiver_id,
+                amount,
+                msg,
+            } => ProposalKind::Transfer {
+                token_id,
+                receiver_id,
+                amount,
+                msg,
+            },
+            ProposalKindV1::SetStakingContract { staking_id } => {
+                ProposalKind::SetStakingContract { staking_id }
+            }
+            ProposalKindV1::AddBounty { bounty } => ProposalKind::AddBounty {
+                bounty: bounty.into(),
+            },
+            ProposalKindV1::BountyDone {
+                bounty_id,
+                receiver_id,
+            } => ProposalKind::BountyDone {
+                bounty_id,
+                receiver_id,
+                milestone: None,
+            },
+            ProposalKindV1::Vote => ProposalKind::Vote,
+            ProposalKindV1::FactoryInfoUpdate { factory_info } => {
+                ProposalKind::FactoryInfoUpdate { factory_info }
+            }
+            ProposalKindV1::ChangePolicyAddOrUpdateRole { role } => {
+                ProposalKind::ChangePolicyAddOrUpdateRole { role: role.into() }
+            }
+            ProposalKindV1::ChangePolicyRemoveRole { role } => {
+                ProposalKind::ChangePolicyRemoveRole { role }
+            }
+            ProposalKindV1::ChangePolicyUpdateDefaultVotePolicy { vote_policy } => {
+                ProposalKind::ChangePolicyUpdateDefaultVotePolicy {
+                    vote_policy: vote_policy.into(),
+                }
+            }
+            ProposalKindV1::ChangePolicyUpdateParameters { parameters } => {
+                ProposalKind::ChangePolicyUpdateParameters { parameters }
+            }
+        }
+    }
 }
 
 impl ProposalKind {
@@ -138,10 +369,75 @@ impl ProposalKind {
                 "policy_update_default_vote_policy"
             }
             ProposalKind::ChangePolicyUpdateParameters { .. } => "policy_update_parameters",
+            ProposalKind::Poll { .. } => "poll",
+            ProposalKind::ConvictionFunding { .. } => "conviction_funding",
+            ProposalKind::TransferNft { .. } => "transfer_nft",
+            ProposalKind::StakeNear { .. } => "stake_near",
+            ProposalKind::UnstakeNear { .. } => "unstake_near",
+            ProposalKind::WithdrawStakedNear { .. } => "withdraw_staked_near",
+            ProposalKind::BulkTransfer { .. } => "bulk_transfer",
+            ProposalKind::CreatePaymentStream { .. } => "create_payment_stream",
+            ProposalKind::CancelPaymentStream { .. } => "cancel_payment_stream",
+            ProposalKind::CreateVestingSchedule { .. } => "create_vesting_schedule",
+            ProposalKind::RevokeVestingSchedule { .. } => "revoke_vesting_schedule",
+            ProposalKind::Swap { .. } => "swap",
+            ProposalKind::ApproveBountyClaim { .. } => "approve_bounty_claim",
+            ProposalKind::UpdateBounty { .. } => "update_bounty",
+            ProposalKind::RemoveBounty { .. } => "remove_bounty",
+        }
+    }
+
+    /// Checks if given policy label applies to this kind of proposal and returns how specific it is.
+    /// Besides the plain label, `FunctionCall` matches scoped labels `call@<receiver>/<method>`,
+    /// where both parts can contain a `*` wildcard and the method must match every action of the call.
+    pub fn match_policy_label(&self, label: &str) -> Option<u8> {
+        if label == self.to_policy_label() {
+            return Some(0);
+        }
+        match self {
+            ProposalKind::FunctionCall {
+                receiver_id,
+                actions,
+            } => {
+                let (receiver, method) = label.strip_prefix("call@")?.split_once('/')?;
+                if wildcard_match(receiver, receiver_id.as_str())
+                    && actions
+                        .iter()
+                        .all(|action| wildcard_match(method, &action.method_name))
+                {
+                    Some(1 + wildcard_specificity(receiver) + wildcard_specificity(method))
+                } else {
+                    None
+                }
+            }
+            _ => None,
         }
     }
 }
 
+/// Matches value against pattern with at most one `*`, which stands for any substring.
+fn wildcard_match(pattern: &str, value: &str) -> bool {
+    match pattern.split_once('*') {
+        Some((prefix, suffix)) => {
+            value.len() >= prefix.len() + suffix.len()
+                && value.starts_with(prefix)
+                && value.ends_with(suffix)
+        }
+        None => pattern == value,
+    }
+}
+
+/// Exact patterns are more specific than partial wildcards, which are more specific than `*`.
+fn wildcard_specificity(pattern: &str) -> u8 {
+    if pattern == "*" {
+        0
+    } else if pattern.contains('*') {
+        1
+    } else {
+        2
+    }
+}
+
 /// Votes recorded in the proposal.
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Debug)]
 #[serde(crate = "near_sdk::serde")]
@@ -149,6 +445,7 @@ pub enum Vote {
     Approve = 0x0,
     Reject = 0x1,
     Remove = 0x2,
+    Abstain = 0x3,
 }
 
 impl From<Action> for Vote {
@@ -157,6 +454,7 @@ impl From<Action> for Vote {
             Action::VoteApprove => Vote::Approve,
             Action::VoteReject => Vote::Reject,
             Action::VoteRemove => Vote::Remove,
+            Action::VoteAbstain => Vote::Abstain,
             _ => unreachable!(),
         }
     }
@@ -175,30 +473,135 @@ pub struct Proposal {
     pub kind: ProposalKind,
     /// Current status of the proposal.
     pub status: ProposalStatus,
-    /// Count of votes per role per decision: yes / no / spam.
-    pub vote_counts: HashMap<String, [Balance; 3]>,
+    /// Count of votes per role per decision: yes / no / spam / abstain.
+    pub vote_counts: HashMap<String, [Balance; 4]>,
     /// Map of who voted and how.
     pub votes: HashMap<AccountId, Vote>,
+    /// Weight each vote added per role, to take it back exactly when the vote changes.
+    pub vote_weights: HashMap<AccountId, HashMap<String, Balance>>,
     /// Submission time (for voting period).
     pub submission_time: U64,
+    /// Proposal in the DAO this proposal was moved from.
+    pub moved_from: Option<ProposalLink>,
+    /// Proposal in the hub this proposal was moved to.
+    pub moved_to: Option<ProposalLink>,
+    /// Time after which the queued proposal can be executed.
+    pub timelock_end: Option<U64>,
+    /// Delegations as of submission, used for token weighted votes.
+    /// Missing for proposals added before snapshots, these use current delegations.
+    pub snapshot: Option<DelegationSnapshot>,
+    /// Ballots and tallies, if this is a poll.
+    pub poll: Option<PollVotes>,
+    /// Stakes and conviction, if this is a conviction funding proposal.
+    pub conviction: Option<Conviction>,
+}
+
+/// Proposal as it was stored before proposals could be moved between DAOs.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub struct ProposalV1 {
+    pub proposer: AccountId,
+    pub description: String,
+    pub kind: ProposalKindV1,
+    pub status: ProposalStatus,
+    pub vote_counts: HashMap<String, [Balance; 3]>,
+    pub votes: HashMap<AccountId, Vote>,
+    pub submission_time: U64,
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize)]
 #[cfg_attr(not(target_arch = "wasm32"), derive(Debug))]
 #[serde(crate = "near_sdk::serde")]
+#[allow(clippy::large_enum_variant)]
 pub enum VersionedProposal {
-    Default(Proposal),
+    Default(ProposalV1),
+    Current(Proposal),
 }
 
 impl From<VersionedProposal> for Proposal {
     fn from(v: VersionedProposal) -> Self {
         match v {
-            VersionedProposal::Default(p) => p,
+            VersionedProposal::Default(p) => Proposal {
+                proposer: p.proposer,
+                description: p.description,
+                kind: p.kind.into(),
+                status: p.status,
+                vote_counts: p
+                    .vote_counts
+                    .into_iter()
+                    .map(|(role, [approve, reject, remove])| (role, [approve, reject, remove, 0]))
+                    .collect(),
+                votes: p.votes,
+                vote_weights: HashMap::default(),
+                submission_time: p.submission_time,
+                moved_from: None,
+                moved_to: None,
+                timelock_end: None,
+                snapshot: None,
+                poll: None,
+                conviction: None,
+            },
+            VersionedProposal::Current(p) => p,
         }
     }
 }
 
 impl Proposal {
+    /// Puts approved proposal into the queue if its kind has execution delay in the policy.
+    pub fn queue_if_delayed(&mut self, policy: &Policy) {
+        let delay = policy.execution_delay(&self.kind);
+        if self.status == ProposalStatus::Approved && delay > 0 {
+            self.status = ProposalStatus::Queued;
+            self.timelock_end = Some(U64::from(env::block_timestamp() + delay));
+        }
+    }
+
+    /// Records the winning option if this is an approved poll.
+    pub fn record_poll_winner(
+        &mut self,
+        policy: &Policy,
+        roles: &[String],
+        total_supply: TotalSupply,
+    ) {
+        if self.status == ProposalStatus::Approved {
+            if let ProposalKind::Poll { .. } = self.kind {
+                let winner = policy.poll_winner(self, roles, total_supply);
+                if let Some(poll) = self.poll.as_mut() {
+                    poll.winner = winner;
+                }
+            }
+        }
+    }
+
+    /// Returns leading option of the poll for the given role and its weight.
+    pub fn poll_leader(&self, role: &str) -> Option<(u32, Balance)> {
+        match &self.kind {
+            ProposalKind::Poll { options, tally } => {
+                self.poll
+                    .as_ref()?
+                    .leader(tally, options.len(), role, &self.vote_weights)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns total weight of the poll ballots for the given role.
+    pub fn poll_participation(&self, role: &str) -> Balance {
+        self.poll.as_ref().map_or(0, |poll| {
+            poll.ballots
+                .keys()
+                .filter_map(|account_id| self.vote_weights.get(account_id)?.get(role))
+                .sum()
+        })
+    }
+
+    /// Checks if the given user has a vote or a ballot on this proposal.
+    pub fn has_voted(&self, account_id: &AccountId) -> bool {
+        self.votes.contains_key(account_id)
+            || matches!(&self.poll, Some(poll) if poll.ballots.contains_key(account_id))
+    }
+
     /// Adds vote of the given user with given `amount` of weight. If user already voted, fails.
     pub fn update_votes(
         &mut self,
@@ -208,20 +611,73 @@ impl Proposal {
         policy: &Policy,
         user_weight: Balance,
     ) {
+        assert!(!self.has_voted(account_id), "ERR_ALREADY_VOTED");
+        let mut weights = HashMap::default();
         for role in roles {
-            let amount = if policy.is_token_weighted(role, &self.kind.to_policy_label().to_string())
-            {
-                user_weight
-            } else {
-                1
-            };
-            self.vote_counts.entry(role.clone()).or_insert([0u128; 3])[vote.clone() as usize] +=
+            let amount = policy.vote_weight(role, &self.kind, user_weight);
+            self.vote_counts.entry(role.clone()).or_insert([0u128; 4])[vote.clone() as usize] +=
                 amount;
+            weights.insert(role.clone(), amount);
         }
         assert!(
             self.votes.insert(account_id.clone(), vote).is_none(),
             "ERR_ALREADY_VOTED"
         );
+        self.vote_weights.insert(account_id.clone(), weights);
+    }
+
+    /// Adds ballot of the given user to the poll. If user already voted, fails.
+    pub fn update_ballot(
+        &mut self,
+        account_id: &AccountId,
+        roles: &[String],
+        ballot: Vec<u32>,
+        policy: &Policy,
+        user_weight: Balance,
+    ) {
+        let (num_options, tally) = match &self.kind {
+            ProposalKind::Poll { options, tally } => (options.len(), tally.clone()),
+            _ => env::panic_str("ERR_NOT_A_POLL"),
+        };
+        tally.assert_valid_ballot(&ballot, num_options);
+        assert!(!self.has_voted(account_id), "ERR_ALREADY_VOTED");
+        let poll = self.poll.get_or_insert_with(PollVotes::default);
+        let mut weights = HashMap::default();
+        for role in roles {
+            let amount = policy.vote_weight(role, &self.kind, user_weight);
+            poll.add_to_tallies(&tally, num_options, role, &ballot, amount);
+            weights.insert(role.clone(), amount);
+        }
+        poll.ballots.insert(account_id.clone(), ballot);
+        self.vote_weights.insert(account_id.clone(), weights);
+    }
+
+    /// Removes vote or ballot of the given user, taking back the weight recorded when they voted.
+    pub fn remove_vote(&mut self, account_id: &AccountId) {
+        let vote = self.votes.remove(account_id);
+        let ballot = self
+            .poll
+            .as_mut()
+            .and_then(|poll| poll.ballots.remove(account_id));
+        assert!(vote.is_some() || ballot.is_some(), "ERR_NO_VOTE");
+        // Votes cast before weights were recorded can't be changed.
+        let weights = self
+            .vote_weights
+            .remove(account_id)
+            .expect("ERR_NO_VOTE_WEIGHT");
+        for (role, amount) in weights {
+            if let Some(vote) = &vote {
+                if let Some(counts) = self.vote_counts.get_mut(&role) {
+                    counts[vote.clone() as usize] -= amount;
+                }
+            }
+            if let (Some(ballot), ProposalKind::Poll { tally, .. }) = (&ballot, &self.kind) {
+                self.poll
+                    .as_mut()
+                    .unwrap()
+                    .remove_from_tallies(tally, &role, ballot, amount);
+            }
+        }
     }
 }
 
@@ -243,7 +699,14 @@ impl From<ProposalInput> for Proposal {
             status: ProposalStatus::InProgress,
             vote_counts: HashMap::default(),
             votes: HashMap::default(),
+            vote_weights: HashMap::default(),
             submission_time: U64::from(env::block_timestamp()),
+            moved_from: None,
+            moved_to: None,
+            timelock_end: None,
+            snapshot: None,
+            poll: None,
+            conviction: None,
         }
     }
 }
@@ -258,43 +721,54 @@ impl Contract {
         memo: String,
         msg: Option<String>,
     ) -> PromiseOrValue<()> {
-        if token_id.is_none() {
-            Promise::new(receiver_id.clone()).transfer(amount).into()
-        } else {
-            if let Some(msg) = msg {
-                ext_fungible_token::ft_transfer_call(
-                    receiver_id.clone(),
-                    U128(amount),
-                    Some(memo),
-                    msg,
-                    token_id.as_ref().unwrap().clone(),
-                    ONE_YOCTO_NEAR,
-                    GAS_FOR_FT_TRANSFER,
-                )
-            } else {
-                ext_fungible_token::ft_transfer(
-                    receiver_id.clone(),
-                    U128(amount),
-                    Some(memo),
-                    token_id.as_ref().unwrap().clone(),
-                    ONE_YOCTO_NEAR,
-                    GAS_FOR_FT_TRANSFER,
-                )
+        match token_id {
+            None => Promise::new(receiver_id.clone()).transfer(amount).into(),
+            Some(token_id)
+                if self
+                    .policy
+                    .get()
+                    .unwrap()
+                    .to_policy()
+                    .register_token_receivers =>
+            {
+                self.internal_registered_ft_transfer(token_id, receiver_id, amount, memo, msg)
+                    .into()
             }
-            .into()
+            Some(token_id) => internal_ft_transfer(token_id, receiver_id, amount, memo, msg).into(),
         }
     }
 
-    fn internal_return_bonds(&mut self, policy: &Policy, proposal: &Proposal) -> Promise {
+    fn internal_return_bonds(
+        &mut self,
+        policy: &Policy,
+        proposal: &Proposal,
+        proposal_id: u64,
+    ) -> Promise {
         match &proposal.kind {
-            ProposalKind::BountyDone { .. } => {
+            ProposalKind::BountyDone {
+                bounty_id,
+                receiver_id,
+                milestone,
+            } if self.internal_bounty_claim_over(*bounty_id, receiver_id, *milestone) => {
                 self.locked_amount -= policy.bounty_bond.0;
                 Promise::new(proposal.proposer.clone()).transfer(policy.bounty_bond.0);
+                Event::BondReturned {
+                    proposal_id,
+                    account_id: &proposal.proposer,
+                    amount: policy.bounty_bond,
+                }
+                .emit();
             }
             _ => {}
         }
 
         self.locked_amount -= policy.proposal_bond.0;
+        Event::BondReturned {
+            proposal_id,
+            account_id: &proposal.proposer,
+            amount: policy.proposal_bond,
+        }
+        .emit();
         Promise::new(proposal.proposer.clone()).transfer(policy.proposal_bond.0)
     }
 
@@ -358,13 +832,16 @@ impl Contract {
                 receiver_id,
                 amount,
                 msg,
-            } => self.internal_payout(
-                &convert_old_to_new_token(token_id),
-                &receiver_id,
-                amount.0,
-                proposal.description.clone(),
-                msg.clone(),
-            ),
+            } => {
+                self.assert_spendable(token_id, amount.0);
+                self.internal_payout(
+                    &convert_old_to_new_token(token_id),
+                    &receiver_id,
+                    amount.0,
+                    proposal.description.clone(),
+                    msg.clone(),
+                )
+            }
             ProposalKind::SetStakingContract { staking_id } => {
                 assert!(self.staking_id.is_none(), "ERR_INVALID_STAKING_CHANGE");
                 self.staking_id = Some(staking_id.clone().into());
@@ -377,8 +854,107 @@ impl Contract {
             ProposalKind::BountyDone {
                 bounty_id,
                 receiver_id,
-            } => self.internal_execute_bounty_payout(*bounty_id, &receiver_id.clone().into(), true),
-            ProposalKind::Vote => PromiseOrValue::Value(()),
+                milestone,
+            } => self.internal_execute_bounty_payout(*bounty_id, receiver_id, *milestone, true),
+            ProposalKind::Vote | ProposalKind::Poll { .. } => PromiseOrValue::Value(()),
+            ProposalKind::ConvictionFunding {
+                token_id,
+                receiver_id,
+                amount,
+            } => {
+                self.assert_spendable(token_id, amount.0);
+                self.internal_payout(
+                    &convert_old_to_new_token(token_id),
+                    receiver_id,
+                    amount.0,
+                    proposal.description.clone(),
+                    None,
+                )
+            }
+            ProposalKind::TransferNft {
+                nft_contract_id,
+                token_id,
+                receiver_id,
+                msg,
+            } => self
+                .internal_transfer_nft(
+                    nft_contract_id,
+                    token_id,
+                    receiver_id,
+                    proposal.description.clone(),
+                    msg.clone(),
+                )
+                .into(),
+            ProposalKind::StakeNear { pool_id, amount } => {
+                self.assert_spendable(&OLD_BASE_TOKEN.to_string(), amount.0);
+                self.internal_call_pool(pool_id, "deposit_and_stake", None, amount.0)
+                    .into()
+            }
+            ProposalKind::UnstakeNear { pool_id, amount } => self
+                .internal_call_pool(pool_id, "unstake", Some(*amount), 0)
+                .into(),
+            ProposalKind::WithdrawStakedNear { pool_id, amount } => self
+                .internal_call_pool(pool_id, "withdraw", Some(*amount), 0)
+                .into(),
+            ProposalKind::BulkTransfer { hash } => {
+                self.internal_start_bulk_transfer(proposal_id, hash);
+                PromiseOrValue::Value(())
+            }
+            ProposalKind::CreatePaymentStream {
+                token_id,
+                receiver_id,
+                amount_per_period,
+                period,
+                start,
+                end,
+                cliff,
+            } => {
+                let stream = PaymentStream::new(
+                    token_id,
+                    receiver_id,
+                    *amount_per_period,
+                    *period,
+                    *start,
+                    *end,
+                    *cliff,
+                );
+                self.internal_create_payment_stream(proposal_id, &stream);
+                PromiseOrValue::Value(())
+            }
+            ProposalKind::CancelPaymentStream { stream_id } => {
+                self.internal_cancel_payment_stream(*stream_id);
+                PromiseOrValue::Value(())
+            }
+            ProposalKind::CreateVestingSchedule {
+                token_id,
+                beneficiary_id,
+                amount,
+                vesting,
+            } => {
+                let schedule = VestingSchedule::new(token_id, beneficiary_id, *amount, vesting);
+                self.internal_create_vesting(proposal_id, &schedule);
+                PromiseOrValue::Value(())
+            }
+            ProposalKind::RevokeVestingSchedule { schedule_id } => {
+                self.internal_revoke_vesting(*schedule_id);
+                PromiseOrValue::Value(())
+            }
+            ProposalKind::Swap { swap } => {
+                self.assert_spendable(&swap.token_in.to_string(), swap.amount_in.0);
+                self.internal_swap(swap).into()
+            }
+            ProposalKind::ApproveBountyClaim {
+                bounty_id,
+                account_id,
+            } => self.internal_review_bounty_application(*bounty_id, account_id, true),
+            ProposalKind::UpdateBounty { bounty_id, update } => {
+                self.internal_update_bounty(*bounty_id, update);
+                PromiseOrValue::Value(())
+            }
+            ProposalKind::RemoveBounty { bounty_id } => {
+                self.internal_remove_bounty(policy, *bounty_id);
+                PromiseOrValue::Value(())
+            }
             ProposalKind::FactoryInfoUpdate { factory_info } => {
                 internal_set_factory_info(factory_info);
                 PromiseOrValue::Value(())
@@ -414,37 +990,66 @@ impl Contract {
                     proposal_id,
                     env::current_account_id(),
                     0,
-                    GAS_FOR_FT_TRANSFER,
+                    GAS_FOR_PROPOSAL_CALLBACK,
                 ))
                 .into(),
-            PromiseOrValue::Value(()) => self.internal_return_bonds(&policy, &proposal).into(),
+            PromiseOrValue::Value(()) => {
+                Event::Executed { proposal_id }.emit();
+                self.internal_return_bonds(policy, proposal, proposal_id)
+                    .into()
+            }
         }
     }
 
     pub(crate) fn internal_callback_proposal_success(
         &mut self,
         proposal: &mut Proposal,
+        proposal_id: u64,
     ) -> PromiseOrValue<()> {
         let policy = self.policy.get().unwrap().to_policy();
-        if let ProposalKind::BountyDone { bounty_id, .. } = proposal.kind {
-            let mut bounty: Bounty = self.bounties.get(&bounty_id).expect("ERR_NO_BOUNTY").into();
-            if bounty.times == 0 {
-                self.bounties.remove(&bounty_id);
-            } else {
-                bounty.times -= 1;
-                self.bounties
-                    .insert(&bounty_id, &VersionedBounty::Default(bounty));
+        match &proposal.kind {
+            ProposalKind::Transfer {
+                token_id, amount, ..
             }
+            | ProposalKind::ConvictionFunding {
+                token_id, amount, ..
+            } => self.internal_token_withdrawn(token_id, amount.0),
+            ProposalKind::BountyDone {
+                bounty_id,
+                receiver_id,
+                milestone,
+            } => self.internal_bounty_paid(*bounty_id, receiver_id, *milestone),
+            _ => {}
+        }
+        self.internal_update_pool_deposit(&proposal.kind);
+        Event::Executed { proposal_id }.emit();
+        if proposal.status != ProposalStatus::Approved {
+            proposal.status = ProposalStatus::Approved;
+            Event::StatusChanged {
+                proposal_id,
+                status: &proposal.status,
+            }
+            .emit();
         }
-        proposal.status = ProposalStatus::Approved;
-        self.internal_return_bonds(&policy, &proposal).into()
+        self.internal_return_bonds(&policy, proposal, proposal_id)
+            .into()
     }
 
+    /// Marks the proposal as failed, so it can be executed again with `Finalize`.
+    /// Claims of failed bounty payouts stay reported until then.
     pub(crate) fn internal_callback_proposal_fail(
         &mut self,
         proposal: &mut Proposal,
+        proposal_id: u64,
     ) -> PromiseOrValue<()> {
+        Event::ExecutionFailed { proposal_id }.emit();
+        self.internal_cancel_spending(proposal, proposal_id);
         proposal.status = ProposalStatus::Failed;
+        Event::StatusChanged {
+            proposal_id,
+            status: &proposal.status,
+        }
+        .emit();
         PromiseOrValue::Value(())
     }
 
@@ -453,37 +1058,82 @@ impl Contract {
         &mut self,
         policy: &Policy,
         proposal: &Proposal,
+        proposal_id: u64,
         return_bonds: bool,
     ) -> PromiseOrValue<()> {
-        if return_bonds {
-            // Return bond to the proposer.
-            self.internal_return_bonds(policy, proposal);
-        }
-        match &proposal.kind {
+        // Claim is removed first, so the bounty bond is returned with the proposal bond.
+        let result = match &proposal.kind {
             ProposalKind::BountyDone {
                 bounty_id,
                 receiver_id,
-            } => {
-                self.internal_execute_bounty_payout(*bounty_id, &receiver_id.clone().into(), false)
-            }
+                milestone,
+            } => self.internal_execute_bounty_payout(*bounty_id, receiver_id, *milestone, false),
+            ProposalKind::ApproveBountyClaim {
+                bounty_id,
+                account_id,
+            } => self.internal_review_bounty_application(*bounty_id, account_id, false),
             _ => PromiseOrValue::Value(()),
+        };
+        if return_bonds {
+            // Return bond to the proposer.
+            self.internal_return_bonds(policy, proposal, proposal_id);
         }
+        result
     }
 
-    pub(crate) fn internal_user_info(&self) -> UserInfo {
-        let account_id = env::predecessor_account_id();
-        UserInfo {
-            amount: self.get_user_weight(&account_id),
-            account_id,
-        }
+    /// Sends given proposal with its bond to the hub DAO.
+    /// Proposal is marked as moved until the hub responds in `on_move_proposal_callback`.
+    fn internal_move_proposal(
+        &mut self,
+        policy: &Policy,
+        proposal: &mut Proposal,
+        proposal_id: u64,
+        hub_id: AccountId,
+    ) {
+        assert!(
+            matches!(proposal.status, ProposalStatus::InProgress),
+            "ERR_PROPOSAL_NOT_IN_PROGRESS"
+        );
+        assert!(
+            proposal.submission_time.0 + policy.proposal_period.0 >= env::block_timestamp(),
+            "ERR_PROPOSAL_EXPIRED"
+        );
+        assert!(
+            !matches!(proposal.kind, ProposalKind::BountyDone { .. }),
+            "ERR_CANT_MOVE_BOUNTY_DONE"
+        );
+        assert_ne!(hub_id, env::current_account_id(), "ERR_INVALID_HUB");
+        let bond = policy.proposal_bond.0;
+        self.locked_amount -= bond;
+        proposal.status = ProposalStatus::Moved;
+        Promise::new(hub_id.clone())
+            .function_call(
+                "add_moved_proposal".to_string(),
+                json!({
+                    "proposal": {
+                        "description": &proposal.description,
+                        "kind": &proposal.kind,
+                    },
+                    "proposer": &proposal.proposer,
+                    "origin_id": proposal_id,
+                })
+                .to_string()
+                .into_bytes(),
+                bond,
+                GAS_FOR_MOVE_PROPOSAL,
+            )
+            .then(ext_self::on_move_proposal_callback(
+                proposal_id,
+                hub_id,
+                U128(bond),
+                env::current_account_id(),
+                0,
+                GAS_FOR_MOVE_CALLBACK,
+            ));
     }
-}
 
-#[near_bindgen]
-impl Contract {
-    /// Add proposal to this DAO.
-    #[payable]
-    pub fn add_proposal(&mut self, proposal: ProposalInput) -> u64 {
+    /// Validates and stores new proposal. Returns id of the proposal.
+    fn internal_add_proposal(&mut self, mut proposal: Proposal) -> u64 {
         // 0. validate bond attached.
         // TODO: consider bond in the token of this DAO.
         let policy = self.policy.get().unwrap().to_policy();
@@ -510,6 +1160,76 @@ impl Contract {
                 self.staking_id.is_none(),
                 "ERR_STAKING_CONTRACT_CANT_CHANGE"
             ),
+            ProposalKind::Poll { options, .. } => assert!(
+                options.len() >= 2 && options.len() <= MAX_POLL_OPTIONS,
+                "ERR_INVALID_POLL_OPTIONS"
+            ),
+            ProposalKind::ConvictionFunding {
+                token_id, amount, ..
+            } => {
+                assert!(token_id == OLD_BASE_TOKEN, "ERR_CONVICTION_NEAR_ONLY");
+                assert!(amount.0 > 0, "ERR_ZERO_AMOUNT");
+            }
+            ProposalKind::StakeNear { amount, .. }
+            | ProposalKind::UnstakeNear { amount, .. }
+            | ProposalKind::WithdrawStakedNear { amount, .. } => {
+                assert!(amount.0 > 0, "ERR_ZERO_AMOUNT");
+            }
+            ProposalKind::BulkTransfer { hash } => {
+                read_bulk_transfer(hash);
+            }
+            ProposalKind::CreatePaymentStream {
+                token_id,
+                receiver_id,
+                amount_per_period,
+                period,
+                start,
+                end,
+                cliff,
+            } => PaymentStream::new(
+                token_id,
+                receiver_id,
+                *amount_per_period,
+                *period,
+                *start,
+                *end,
+                *cliff,
+            )
+            .assert_valid(),
+            ProposalKind::CancelPaymentStream { stream_id } => assert!(
+                self.payment_streams.get(stream_id).is_some(),
+                "ERR_NO_PAYMENT_STREAM"
+            ),
+            ProposalKind::CreateVestingSchedule {
+                token_id,
+                beneficiary_id,
+                amount,
+                vesting,
+            } => VestingSchedule::new(token_id, beneficiary_id, *amount, vesting).assert_valid(),
+            ProposalKind::RevokeVestingSchedule { schedule_id } => assert!(
+                self.vesting_schedules.get(schedule_id).is_some(),
+                "ERR_NO_VESTING"
+            ),
+            ProposalKind::AddBounty { bounty } => bounty.assert_valid(),
+            ProposalKind::Swap { swap } => swap.assert_valid(),
+            ProposalKind::ApproveBountyClaim {
+                bounty_id,
+                account_id,
+            } => assert!(
+                self.bounty_applications
+                    .get(bounty_id)
+                    .unwrap_or_default()
+                    .iter()
+                    .any(|application| application.account_id == *account_id),
+                "ERR_NO_BOUNTY_APPLICATION"
+            ),
+            ProposalKind::UpdateBounty { bounty_id, update } => {
+                let bounty: Bounty = self.bounties.get(bounty_id).expect("ERR_NO_BOUNTY").into();
+                bounty.updated(update).assert_valid();
+            }
+            ProposalKind::RemoveBounty { bounty_id } => {
+                assert!(self.bounties.get(bounty_id).is_some(), "ERR_NO_BOUNTY")
+            }
             // TODO: add more verifications.
             _ => {}
         };
@@ -527,58 +1247,196 @@ impl Contract {
         );
 
         // 3. Actually add proposal to the current list of proposals.
+        self.assert_quadratic_weight_ready(&policy, &proposal.kind);
         let id = self.last_proposal_id;
+        proposal.snapshot = Some(self.internal_delegation_snapshot(id));
+        Event::ProposalAdded {
+            proposal_id: id,
+            proposer: &proposal.proposer,
+            kind: proposal.kind.to_policy_label(),
+        }
+        .emit();
         self.proposals
-            .insert(&id, &VersionedProposal::Default(proposal.into()));
+            .insert(&id, &VersionedProposal::Current(proposal));
         self.last_proposal_id += 1;
         self.locked_amount += env::attached_deposit();
         id
     }
 
+    pub(crate) fn internal_user_info(&self) -> UserInfo {
+        let account_id = env::predecessor_account_id();
+        UserInfo {
+            amount: self.get_user_weight(&account_id),
+            account_id,
+        }
+    }
+
+    /// Caller's info and total delegated amounts as of given proposal submission.
+    fn internal_proposal_user_info(&self, proposal: &Proposal) -> (UserInfo, TotalSupply) {
+        let account_id = env::predecessor_account_id();
+        match &proposal.snapshot {
+            Some(snapshot) => (
+                UserInfo {
+                    amount: self.get_user_weight_at(&account_id, snapshot.checkpoint_id),
+                    account_id,
+                },
+                snapshot.total_supply(),
+            ),
+            None => (self.internal_user_info(), self.internal_total_supply()),
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Add proposal to this DAO.
+    #[payable]
+    pub fn add_proposal(&mut self, proposal: ProposalInput) -> u64 {
+        self.internal_add_proposal(proposal.into())
+    }
+
+    /// Add proposal moved from another DAO via `MoveToHub` action.
+    /// Caller is the origin DAO, which must be listed in `moved_proposal_sources` of the policy
+    /// and allowed to add this kind of proposal here.
+    /// Original proposer is kept, so the bond forwarded by the origin DAO is returned to them.
+    #[payable]
+    pub fn add_moved_proposal(
+        &mut self,
+        proposal: ProposalInput,
+        proposer: AccountId,
+        origin_id: u64,
+    ) -> u64 {
+        assert!(
+            self.policy
+                .get()
+                .unwrap()
+                .to_policy()
+                .moved_proposal_sources
+                .contains(&env::predecessor_account_id()),
+            "ERR_NOT_MOVED_PROPOSAL_SOURCE"
+        );
+        let mut proposal: Proposal = proposal.into();
+        proposal.moved_from = Some(ProposalLink {
+            dao_id: env::predecessor_account_id(),
+            proposal_id: origin_id,
+        });
+        proposal.proposer = proposer;
+        self.internal_add_proposal(proposal)
+    }
+
     /// Act on given proposal by id, if permissions allow.
     /// Memo is logged but not stored in the state. Can be used to leave notes or explain the action.
     pub fn act_proposal(&mut self, id: u64, action: Action, memo: Option<String>) {
         let mut proposal: Proposal = self.proposals.get(&id).expect("ERR_NO_PROPOSAL").into();
-        let policy = self.policy.get().unwrap().to_policy();
+        // Transfers under spending limits are decided with lighter vote policies.
+        let policy =
+            self.internal_spending_policy(self.policy.get().unwrap().to_policy(), &proposal);
         // Check permissions for the given action.
-        let (roles, allowed) =
-            policy.can_execute_action(self.internal_user_info(), &proposal.kind, &action);
-        assert!(allowed, "ERR_PERMISSION_DENIED");
+        // Weights are taken as of proposal submission, so tokens delegated later don't count.
+        let (user, total_supply) = self.internal_proposal_user_info(&proposal);
+        self.assert_quadratic_weight_ready(&policy, &proposal.kind);
+        let user_weight = user.amount;
+        let was_approved = matches!(
+            proposal.status,
+            ProposalStatus::Approved | ProposalStatus::Queued
+        );
+        let (roles, allowed) = policy.can_execute_action(user, &proposal.kind, &action);
+        // Anyone can execute queued proposal once its delay has passed,
+        // voters can always retract their own vote or stake.
+        assert!(
+            allowed
+                || matches!(
+                    action,
+                    Action::Execute | Action::RetractVote | Action::UnstakeConviction
+                ),
+            "ERR_PERMISSION_DENIED"
+        );
         let sender_id = env::predecessor_account_id();
         // Update proposal given action. Returns true if should be updated in storage.
         let update = match action {
             Action::AddProposal => env::panic_str("ERR_WRONG_ACTION"),
             Action::RemoveProposal => {
                 self.proposals.remove(&id);
+                Event::ProposalRemoved { proposal_id: id }.emit();
                 false
             }
-            Action::VoteApprove | Action::VoteReject | Action::VoteRemove => {
+            Action::VoteApprove
+            | Action::VoteReject
+            | Action::VoteRemove
+            | Action::VoteAbstain
+            | Action::RetractVote
+            | Action::CastBallot { .. } => {
                 assert!(
                     matches!(proposal.status, ProposalStatus::InProgress),
                     "ERR_PROPOSAL_NOT_READY_FOR_VOTE"
                 );
-                proposal.update_votes(
-                    &sender_id,
-                    &roles,
-                    Vote::from(action),
-                    &policy,
-                    self.get_user_weight(&sender_id),
-                );
+                if let Action::RetractVote = action {
+                    assert!(policy.allow_vote_change, "ERR_VOTE_CHANGE_NOT_ALLOWED");
+                    proposal.remove_vote(&sender_id);
+                    Event::VoteRetracted {
+                        proposal_id: id,
+                        account_id: &sender_id,
+                    }
+                    .emit();
+                } else if let Action::CastBallot { ballot } = action {
+                    if policy.allow_vote_change && proposal.has_voted(&sender_id) {
+                        proposal.remove_vote(&sender_id);
+                    }
+                    Event::BallotCast {
+                        proposal_id: id,
+                        account_id: &sender_id,
+                        ballot: &ballot,
+                    }
+                    .emit();
+                    proposal.update_ballot(&sender_id, &roles, ballot, &policy, user_weight);
+                } else {
+                    // Polls can only be flagged for removal, options are voted with ballots.
+                    assert!(
+                        !matches!(proposal.kind, ProposalKind::Poll { .. })
+                            || matches!(action, Action::VoteRemove),
+                        "ERR_POLL_NEEDS_BALLOT"
+                    );
+                    // Conviction funding can only be flagged for removal, it's supported with stakes.
+                    assert!(
+                        !matches!(proposal.kind, ProposalKind::ConvictionFunding { .. })
+                            || matches!(action, Action::VoteRemove),
+                        "ERR_CONVICTION_NEEDS_STAKE"
+                    );
+                    if policy.allow_vote_change && proposal.has_voted(&sender_id) {
+                        proposal.remove_vote(&sender_id);
+                    }
+                    let vote = Vote::from(action);
+                    Event::VoteCast {
+                        proposal_id: id,
+                        account_id: &sender_id,
+                        vote: &vote,
+                    }
+                    .emit();
+                    proposal.update_votes(&sender_id, &roles, vote, &policy, user_weight);
+                }
                 // Updates proposal status with new votes using the policy.
-                proposal.status =
-                    policy.proposal_status(&proposal, roles, self.total_delegation_amount);
+                proposal.status = policy.proposal_status(&proposal, roles.clone(), total_supply);
+                proposal.record_poll_winner(&policy, &roles, total_supply);
+                proposal.queue_if_delayed(&policy);
+                if proposal.status != ProposalStatus::InProgress {
+                    Event::StatusChanged {
+                        proposal_id: id,
+                        status: &proposal.status,
+                    }
+                    .emit();
+                }
                 if proposal.status == ProposalStatus::Approved {
                     self.internal_execute_proposal(&policy, &proposal, id);
                     true
                 } else if proposal.status == ProposalStatus::Removed {
-                    self.internal_reject_proposal(&policy, &proposal, false);
+                    self.internal_reject_proposal(&policy, &proposal, id, false);
                     self.proposals.remove(&id);
                     false
                 } else if proposal.status == ProposalStatus::Rejected {
-                    self.internal_reject_proposal(&policy, &proposal, true);
+                    self.internal_reject_proposal(&policy, &proposal, id, true);
                     true
                 } else {
-                    // Still in progress or expired.
+                    // Still in progress, queued or expired.
                     true
                 }
             }
@@ -590,29 +1448,123 @@ impl Contract {
             //  - if the number of votes in the group has changed (new members has been added) -
             //      the proposal can loose it's approved state. In this case new proposal needs to be made, this one can only expire.
             Action::Finalize => {
-                proposal.status = policy.proposal_status(
-                    &proposal,
-                    policy.roles.iter().map(|r| r.name.clone()).collect(),
-                    self.total_delegation_amount,
-                );
+                let prev_status = proposal.status.clone();
+                let roles: Vec<String> = policy.roles.iter().map(|r| r.name.clone()).collect();
+                proposal.status = match proposal.kind {
+                    ProposalKind::ConvictionFunding { .. } => {
+                        self.internal_conviction_status(&policy, &proposal)
+                    }
+                    _ => policy.proposal_status(&proposal, roles.clone(), total_supply),
+                };
+                proposal.record_poll_winner(&policy, &roles, total_supply);
+                // Failed proposals have already waited in the queue before the first execution.
+                if prev_status == ProposalStatus::InProgress {
+                    proposal.queue_if_delayed(&policy);
+                }
+                if proposal.status != prev_status {
+                    Event::StatusChanged {
+                        proposal_id: id,
+                        status: &proposal.status,
+                    }
+                    .emit();
+                }
                 match proposal.status {
                     ProposalStatus::Approved => {
                         self.internal_execute_proposal(&policy, &proposal, id);
                     }
                     ProposalStatus::Expired => {
-                        self.internal_reject_proposal(&policy, &proposal, true);
+                        self.internal_reject_proposal(&policy, &proposal, id, true);
                     }
+                    ProposalStatus::Queued => {}
                     _ => {
                         env::panic_str("ERR_PROPOSAL_NOT_EXPIRED_OR_FAILED");
                     }
                 }
                 true
             }
-            Action::MoveToHub => false,
+            Action::Execute => {
+                assert!(
+                    matches!(proposal.status, ProposalStatus::Queued),
+                    "ERR_PROPOSAL_NOT_QUEUED"
+                );
+                assert!(
+                    env::block_timestamp() >= proposal.timelock_end.expect("INTERNAL_FAIL").0,
+                    "ERR_PROPOSAL_TIMELOCKED"
+                );
+                proposal.status = ProposalStatus::Approved;
+                Event::StatusChanged {
+                    proposal_id: id,
+                    status: &proposal.status,
+                }
+                .emit();
+                self.internal_execute_proposal(&policy, &proposal, id);
+                true
+            }
+            Action::Veto => {
+                assert!(
+                    matches!(proposal.status, ProposalStatus::Queued),
+                    "ERR_PROPOSAL_NOT_QUEUED"
+                );
+                assert!(
+                    env::block_timestamp() < proposal.timelock_end.expect("INTERNAL_FAIL").0,
+                    "ERR_VETO_PERIOD_PASSED"
+                );
+                proposal.status = ProposalStatus::Rejected;
+                Event::StatusChanged {
+                    proposal_id: id,
+                    status: &proposal.status,
+                }
+                .emit();
+                self.internal_cancel_spending(&proposal, id);
+                self.internal_reject_proposal(&policy, &proposal, id, true);
+                true
+            }
+            Action::StakeConviction { amount } => {
+                assert!(
+                    matches!(proposal.status, ProposalStatus::InProgress),
+                    "ERR_PROPOSAL_NOT_READY_FOR_VOTE"
+                );
+                self.internal_stake_conviction(&policy, &mut proposal, id, &sender_id, amount.0);
+                proposal.status = self.internal_conviction_status(&policy, &proposal);
+                proposal.queue_if_delayed(&policy);
+                if proposal.status != ProposalStatus::InProgress {
+                    Event::StatusChanged {
+                        proposal_id: id,
+                        status: &proposal.status,
+                    }
+                    .emit();
+                }
+                if proposal.status == ProposalStatus::Approved {
+                    self.internal_execute_proposal(&policy, &proposal, id);
+                }
+                true
+            }
+            Action::UnstakeConviction => {
+                self.internal_unstake_conviction(&policy, &mut proposal, id, &sender_id);
+                true
+            }
+            Action::MoveToHub { hub_id } => {
+                self.internal_move_proposal(&policy, &mut proposal, id, hub_id);
+                Event::StatusChanged {
+                    proposal_id: id,
+                    status: &proposal.status,
+                }
+                .emit();
+                true
+            }
         };
+        // Transfers count against spending limits from approval, so queued ones are included.
+        if !was_approved
+            && matches!(
+                proposal.status,
+                ProposalStatus::Approved | ProposalStatus::Queued
+            )
+        {
+            self.internal_record_spending(&policy, &proposal, id);
+        }
         if update {
             self.proposals
-                .insert(&id, &VersionedProposal::Default(proposal));
+                .insert(&id, &VersionedProposal::Current(proposal));
         }
         if let Some(memo) = memo {
             log!("Memo: {}", memo);
@@ -637,11 +1589,81 @@ impl Contract {
         );
         let result = match env::promise_result(0) {
             PromiseResult::NotReady => unreachable!(),
-            PromiseResult::Successful(_) => self.internal_callback_proposal_success(&mut proposal),
-            PromiseResult::Failed => self.internal_callback_proposal_fail(&mut proposal),
+            PromiseResult::Successful(value) => {
+                if let ProposalKind::TransferNft {
+                    nft_contract_id,
+                    token_id,
+                    ..
+                } = &proposal.kind
+                {
+                    // `nft_transfer_call` returns false if the receiver sent the token back.
+                    if value != b"false" {
+                        self.internal_nft_withdrawn(nft_contract_id, token_id);
+                    }
+                }
+                // `on_swap_callback` returns false if the swap failed.
+                if matches!(proposal.kind, ProposalKind::Swap { .. }) && value == b"false" {
+                    self.internal_callback_proposal_fail(&mut proposal, proposal_id)
+                } else {
+                    self.internal_callback_proposal_success(&mut proposal, proposal_id)
+                }
+            }
+            PromiseResult::Failed => {
+                self.internal_callback_proposal_fail(&mut proposal, proposal_id)
+            }
         };
         self.proposals
-            .insert(&proposal_id, &VersionedProposal::Default(proposal.into()));
+            .insert(&proposal_id, &VersionedProposal::Current(proposal));
         result
     }
+
+    /// Receiving callback after the proposal has been sent to the hub.
+    /// If the hub accepted it, the bond stays with the hub and this proposal points to the new one.
+    /// Otherwise the hub refunds the bond, which gets locked again, and the proposal is back in progress.
+    #[private]
+    pub fn on_move_proposal_callback(&mut self, proposal_id: u64, hub_id: AccountId, bond: U128) {
+        let mut proposal: Proposal = self
+            .proposals
+            .get(&proposal_id)
+            .expect("ERR_NO_PROPOSAL")
+            .into();
+        assert_eq!(
+            env::promise_results_count(),
+            1,
+            "ERR_UNEXPECTED_CALLBACK_PROMISES"
+        );
+        match env::promise_result(0) {
+            PromiseResult::NotReady => unreachable!(),
+            PromiseResult::Successful(value) => {
+                let hub_proposal_id: u64 =
+                    serde_json::from_slice(&value).expect("ERR_INVALID_HUB_RESPONSE");
+                Event::ProposalMoved {
+                    proposal_id,
+                    hub_id: &hub_id,
+                    hub_proposal_id,
+                }
+                .emit();
+                proposal.moved_to = Some(ProposalLink {
+                    dao_id: hub_id,
+                    proposal_id: hub_proposal_id,
+                });
+            }
+            PromiseResult::Failed => {
+                self.locked_amount += bond.0;
+                proposal.status = ProposalStatus::InProgress;
+                Event::ProposalMoveFailed {
+                    proposal_id,
+                    hub_id: &hub_id,
+                }
+                .emit();
+                Event::StatusChanged {
+                    proposal_id,
+                    status: &proposal.status,
+                }
+                .emit();
+            }
+        }
+        self.proposals
+            .insert(&proposal_id, &VersionedProposal::Current(proposal));
+    }
 }
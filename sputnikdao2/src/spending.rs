@@ -0,0 +1,254 @@
+//! Spending limits: caps on `Transfer` payouts per token in a rolling period.
+//! Transfers that fit under a role's cap are decided by that role with a lighter quorum and threshold,
+//! larger ones escalate to the role's regular vote policy.
+
+use near_sdk::json_types::U64;
+
+use crate::policy::WeightOrRatio;
+use crate::*;
+
+/// Cap on payouts of a token by `Transfer` proposals in a rolling period.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct SpendingLimit {
+    /// Max amount paid out in the period, including the proposed transfer.
+    pub amount: U128,
+    /// Length of the rolling period.
+    pub period: U64,
+    /// Quorum that replaces the one of the regular vote policy under the cap.
+    pub quorum: U128,
+    /// Threshold that replaces the one of the regular vote policy under the cap.
+    pub threshold: WeightOrRatio,
+}
+
+/// Payout of a `Transfer` proposal, counted from its approval so queued payouts are included.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct SpendingRecord {
+    pub proposal_id: u64,
+    pub timestamp: u64,
+    pub amount: Balance,
+}
+
+impl Contract {
+    /// Amount of the token paid out or approved to be paid out by transfers in the last `period`.
+    pub(crate) fn internal_spent_amount(&self, token_id: &OldAccountId, period: u64) -> Balance {
+        let since = env::block_timestamp().saturating_sub(period);
+        self.spending
+            .get(token_id)
+            .unwrap_or_default()
+            .iter()
+            .filter(|record| record.timestamp >= since)
+            .map(|record| record.amount)
+            .sum()
+    }
+
+    /// Records approved transfer, if the token has spending limits.
+    /// Records older than the longest period of the token's limits are dropped.
+    pub(crate) fn internal_record_spending(
+        &mut self,
+        policy: &Policy,
+        proposal: &Proposal,
+        proposal_id: u64,
+    ) {
+        let (token_id, amount) = match &proposal.kind {
+            ProposalKind::Transfer {
+                token_id, amount, ..
+            } => (token_id, amount.0),
+            _ => return,
+        };
+        let longest_period = match policy
+            .roles
+            .iter()
+            .filter_map(|role| role.spending_limits.get(token_id))
+            .chain(policy.spending_limits.get(token_id))
+            .map(|limit| limit.period.0)
+            .max()
+        {
+            Some(period) => period,
+            None => return,
+        };
+        let now = env::block_timestamp();
+        let mut records = self.spending.get(token_id).unwrap_or_default();
+        records.retain(|record| record.timestamp >= now.saturating_sub(longest_period));
+        records.push(SpendingRecord {
+            proposal_id,
+            timestamp: now,
+            amount,
+        });
+        self.spending.insert(token_id, &records);
+    }
+
+    /// Drops the record of approved transfer that was vetoed or whose payout failed.
+    pub(crate) fn internal_cancel_spending(&mut self, proposal: &Proposal, proposal_id: u64) {
+        let token_id = match &proposal.kind {
+            ProposalKind::Transfer { token_id, .. } => token_id,
+            _ => return,
+        };
+        if let Some(mut records) = self.spending.get(token_id) {
+            records.retain(|record| record.proposal_id != proposal_id);
+            self.spending.insert(token_id, &records);
+        }
+    }
+
+    /// Policy to decide given proposal with. For a `Transfer` that fits under the spending limit
+    /// of a role, that role's vote policy for transfers gets the quorum and threshold of the limit.
+    /// Only vote policies change, so permissions and weights stay the same.
+    pub(crate) fn internal_spending_policy(
+        &self,
+        mut policy: Policy,
+        proposal: &Proposal,
+    ) -> Policy {
+        let (token_id, amount) = match &proposal.kind {
+            ProposalKind::Transfer {
+                token_id, amount, ..
+            } => (token_id, amount.0),
+            _ => return policy,
+        };
+        let label = proposal.kind.to_policy_label().to_string();
+        for role in policy.roles.iter_mut() {
+            let limit = match role
+                .spending_limits
+                .get(token_id)
+                .or(policy.spending_limits.get(token_id))
+            {
+                Some(limit) => limit.clone(),
+                None => continue,
+            };
+            if self.internal_spent_amount(token_id, limit.period.0) + amount > limit.amount.0 {
+                continue;
+            }
+            let mut vote_policy = role
+                .vote_policy_for(&proposal.kind)
+                .unwrap_or(&policy.default_vote_policy)
+                .clone();
+            vote_policy.quorum = limit.quorum;
+            vote_policy.threshold = limit.threshold;
+            role.vote_policy.insert(label.clone(), vote_policy);
+        }
+        policy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, PromiseResult};
+    use near_sdk_sim::to_yocto;
+
+    use crate::test_utils::resolve_proposal;
+
+    use super::*;
+
+    fn add_transfer(context: &mut VMContextBuilder, contract: &mut Contract) -> u64 {
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(to_yocto("1"))
+            .build());
+        let id = contract.add_proposal(ProposalInput {
+            description: "test".to_string(),
+            kind: ProposalKind::Transfer {
+                token_id: String::from(OLD_BASE_TOKEN),
+                receiver_id: accounts(4),
+                amount: U128(to_yocto("100")),
+                msg: None,
+            },
+        });
+        testing_env!(context.attached_deposit(0).build());
+        id
+    }
+
+    const DAY: u64 = 1_000_000_000 * 60 * 60 * 24;
+
+    /// Council of three where any single member can approve up to 150 NEAR a day.
+    fn setup_spending_limit(context: &mut VMContextBuilder) -> Contract {
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let mut policy =
+            VersionedPolicy::Default(vec![accounts(1), accounts(2), accounts(3)]).upgrade();
+        policy.to_policy_mut().roles[1].spending_limits.insert(
+            String::from(OLD_BASE_TOKEN),
+            SpendingLimit {
+                amount: U128(to_yocto("150")),
+                period: U64(DAY),
+                quorum: U128(0),
+                threshold: WeightOrRatio::Weight(U128(1)),
+            },
+        );
+        Contract::new(Config::test_config(), policy)
+    }
+
+    #[test]
+    fn test_spending_limit() {
+        let mut context = VMContextBuilder::new();
+        let mut contract = setup_spending_limit(&mut context);
+
+        let id = add_transfer(&mut context, &mut contract);
+        contract.act_proposal(id, Action::VoteApprove, None);
+        assert_eq!(
+            contract.get_proposal(id).proposal.status,
+            ProposalStatus::Approved
+        );
+        // Approved transfer counts before its payout resolves.
+        assert_eq!(
+            contract.get_spending_limits(String::from(OLD_BASE_TOKEN))[0].spent,
+            U128(to_yocto("100"))
+        );
+        resolve_proposal(
+            &context,
+            &mut contract,
+            id,
+            PromiseResult::Successful(vec![]),
+        );
+        let limits = contract.get_spending_limits(String::from(OLD_BASE_TOKEN));
+        assert_eq!(limits.len(), 1);
+        assert_eq!(limits[0].role, "council");
+        assert_eq!(limits[0].spent, U128(to_yocto("100")));
+
+        // Next transfer goes over the cap and needs the majority of the council.
+        let id = add_transfer(&mut context, &mut contract);
+        contract.act_proposal(id, Action::VoteApprove, None);
+        assert_eq!(
+            contract.get_proposal(id).proposal.status,
+            ProposalStatus::InProgress
+        );
+
+        // Once the first payout is out of the period, the cap is free again.
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(DAY + 1)
+            .build());
+        assert_eq!(
+            contract.get_spending_limits(String::from(OLD_BASE_TOKEN))[0].spent,
+            U128(0)
+        );
+        let id = add_transfer(&mut context, &mut contract);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.act_proposal(id, Action::VoteApprove, None);
+        assert_eq!(
+            contract.get_proposal(id).proposal.status,
+            ProposalStatus::Approved
+        );
+    }
+
+    #[test]
+    fn test_spending_limit_failed_payout() {
+        let mut context = VMContextBuilder::new();
+        let mut contract = setup_spending_limit(&mut context);
+
+        let id = add_transfer(&mut context, &mut contract);
+        contract.act_proposal(id, Action::VoteApprove, None);
+        // Cap is taken by the pending payout.
+        let next_id = add_transfer(&mut context, &mut contract);
+        contract.act_proposal(next_id, Action::VoteApprove, None);
+        assert_eq!(
+            contract.get_proposal(next_id).proposal.status,
+            ProposalStatus::InProgress
+        );
+
+        resolve_proposal(&context, &mut contract, id, PromiseResult::Failed);
+        assert_eq!(
+            contract.get_spending_limits(String::from(OLD_BASE_TOKEN))[0].spent,
+            U128(0)
+        );
+    }
+}
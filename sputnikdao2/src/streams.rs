@@ -0,0 +1,334 @@
+//! Payment streams: recurring payouts that vest every period and are claimed by the receiver.
+//! Amounts that can still be paid out are reserved in the treasury, see `internal_reserve`.
+
+use near_sdk::json_types::U64;
+use near_sdk::PromiseOrValue;
+
+use crate::events::Event;
+use crate::types::{convert_old_to_new_token, GAS_FOR_STREAM_CLAIM_CALLBACK};
+use crate::*;
+
+/// Stream paying `amount_per_period` to `receiver_id` for every full period between `start` and `end`.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct PaymentStream {
+    /// Can be "" for $NEAR or a valid account id.
+    pub token_id: OldAccountId,
+    pub receiver_id: AccountId,
+    pub amount_per_period: U128,
+    /// Length of a period in nanoseconds.
+    pub period: U64,
+    pub start: U64,
+    /// Set to the time of cancellation if the stream was cancelled earlier.
+    pub end: U64,
+    /// Nothing can be claimed before this time, afterwards all periods since `start` are vested.
+    pub cliff: U64,
+    /// Amount already claimed by the receiver.
+    pub claimed: U128,
+}
+
+impl PaymentStream {
+    /// New stream starting at `start`, nothing claimed yet.
+    pub fn new(
+        token_id: &OldAccountId,
+        receiver_id: &AccountId,
+        amount_per_period: U128,
+        period: U64,
+        start: U64,
+        end: U64,
+        cliff: U64,
+    ) -> Self {
+        Self {
+            token_id: token_id.clone(),
+            receiver_id: receiver_id.clone(),
+            amount_per_period,
+            period,
+            start,
+            end,
+            cliff,
+            claimed: U128(0),
+        }
+    }
+
+    /// Amount the stream pays out in total.
+    pub fn total(&self) -> Balance {
+        (self.end.0 - self.start.0) as Balance / self.period.0 as Balance * self.amount_per_period.0
+    }
+
+    /// Amount vested at given time.
+    pub fn vested(&self, timestamp: u64) -> Balance {
+        if timestamp < self.cliff.0 {
+            return 0;
+        }
+        let elapsed = timestamp.min(self.end.0).saturating_sub(self.start.0);
+        elapsed as Balance / self.period.0 as Balance * self.amount_per_period.0
+    }
+
+    /// Amount the receiver can claim now.
+    pub fn claimable(&self) -> Balance {
+        self.vested(env::block_timestamp()) - self.claimed.0
+    }
+
+    /// Panics if the stream is not valid.
+    pub(crate) fn assert_valid(&self) {
+        assert!(
+            self.amount_per_period.0 > 0
+                && self.period.0 > 0
+                && self.start.0 <= self.cliff.0
+                && self.cliff.0 <= self.end.0
+                && self.end.0 - self.start.0 >= self.period.0,
+            "ERR_INVALID_PAYMENT_STREAM"
+        );
+    }
+}
+
+impl Contract {
+    /// Starts the stream of the approved proposal, reserving its total amount.
+    /// Fails if the treasury doesn't have enough of it available.
+    pub(crate) fn internal_create_payment_stream(
+        &mut self,
+        stream_id: u64,
+        stream: &PaymentStream,
+    ) {
+        let total = stream.total();
+        assert!(
+            self.internal_available_amount(&stream.token_id) >= total,
+            "ERR_NOT_ENOUGH_FUNDS"
+        );
+        self.internal_reserve(&stream.token_id, total);
+        self.payment_streams.insert(&stream_id, stream);
+    }
+
+    /// Ends the stream now. Vested amount can still be claimed, the rest is released.
+    pub(crate) fn internal_cancel_payment_stream(&mut self, stream_id: u64) {
+        let mut stream = self
+            .payment_streams
+            .get(&stream_id)
+            .expect("ERR_NO_PAYMENT_STREAM");
+        let now = env::block_timestamp();
+        let total = stream.total();
+        stream.end = if now < stream.cliff.0 {
+            stream.start
+        } else {
+            U64(stream.end.0.min(now))
+        };
+        self.internal_release(&stream.token_id, total - stream.total());
+        self.payment_streams.insert(&stream_id, &stream);
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Pays out what has vested in the stream since the last claim. Only the receiver can call this.
+    pub fn stream_claim(&mut self, stream_id: u64) -> Promise {
+        let mut stream = self
+            .payment_streams
+            .get(&stream_id)
+            .expect("ERR_NO_PAYMENT_STREAM");
+        assert_eq!(
+            env::predecessor_account_id(),
+            stream.receiver_id,
+            "ERR_NOT_STREAM_RECEIVER"
+        );
+        let amount = stream.claimable();
+        assert!(amount > 0, "ERR_NOTHING_TO_CLAIM");
+        stream.claimed = U128(stream.claimed.0 + amount);
+        self.payment_streams.insert(&stream_id, &stream);
+        self.internal_release(&stream.token_id, amount);
+        let payout = match self.internal_payout(
+            &convert_old_to_new_token(&stream.token_id),
+            &stream.receiver_id,
+            amount,
+            format!("Payment stream {}", stream_id),
+            None,
+        ) {
+            PromiseOrValue::Promise(promise) => promise,
+            PromiseOrValue::Value(()) => unreachable!(),
+        };
+        payout.then(ext_self::on_stream_claim_callback(
+            stream_id,
+            U128(amount),
+            env::current_account_id(),
+            0,
+            GAS_FOR_STREAM_CLAIM_CALLBACK,
+        ))
+    }
+
+    /// Receiving callback after the payout of a stream claim. Reverts the claim if the payout failed.
+    #[private]
+    pub fn on_stream_claim_callback(&mut self, stream_id: u64, amount: U128) {
+        assert_eq!(
+            env::promise_results_count(),
+            1,
+            "ERR_UNEXPECTED_CALLBACK_PROMISES"
+        );
+        let mut stream = self
+            .payment_streams
+            .get(&stream_id)
+            .expect("ERR_NO_PAYMENT_STREAM");
+        match env::promise_result(0) {
+            PromiseResult::Successful(_) => {
+                self.internal_token_withdrawn(&stream.token_id, amount.0);
+                Event::PaymentStreamClaimed {
+                    stream_id,
+                    receiver_id: &stream.receiver_id,
+                    amount,
+                }
+                .emit();
+            }
+            _ => {
+                stream.claimed = U128(stream.claimed.0 - amount.0);
+                self.payment_streams.insert(&stream_id, &stream);
+                self.internal_reserve(&stream.token_id, amount.0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+    use near_sdk_sim::to_yocto;
+
+    use crate::test_utils::{execute, promise_result};
+
+    use super::*;
+
+    const DAY: u64 = 86_400_000_000_000;
+
+    fn claim(context: &mut VMContextBuilder, contract: &mut Contract, id: u64, success: bool) {
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        let amount = contract.get_payment_stream(id).claimable;
+        contract.stream_claim(id);
+        promise_result(
+            context,
+            if success {
+                PromiseResult::Successful(vec![])
+            } else {
+                PromiseResult::Failed
+            },
+        );
+        contract.on_stream_claim_callback(id, amount);
+    }
+
+    #[test]
+    fn test_payment_stream() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context
+            .current_account_id(accounts(0))
+            .predecessor_account_id(accounts(1))
+            .account_balance(to_yocto("1000"))
+            .build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1)]),
+        );
+        let available = contract.get_available_amount().0;
+        // 12 monthly payments of 10 NEAR with a cliff after 3 months.
+        let id = execute(
+            &mut context,
+            &mut contract,
+            ProposalKind::CreatePaymentStream {
+                token_id: String::from(OLD_BASE_TOKEN),
+                receiver_id: accounts(2),
+                amount_per_period: U128(to_yocto("10")),
+                period: U64(30 * DAY),
+                start: U64(0),
+                end: U64(360 * DAY),
+                cliff: U64(90 * DAY),
+            },
+        );
+        assert!(contract.get_available_amount().0 < available - to_yocto("119"));
+
+        context.block_timestamp(89 * DAY);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        assert_eq!(contract.get_payment_stream(id).claimable, U128(0));
+
+        // A failed payout can be claimed again.
+        context.block_timestamp(100 * DAY);
+        claim(&mut context, &mut contract, id, false);
+        assert_eq!(contract.get_payment_stream(id).stream.claimed, U128(0));
+        claim(&mut context, &mut contract, id, true);
+        assert_eq!(
+            contract.get_payment_stream(id).stream.claimed,
+            U128(to_yocto("30"))
+        );
+
+        // Cancelling keeps vested periods claimable and releases the rest.
+        context.block_timestamp(150 * DAY);
+        execute(
+            &mut context,
+            &mut contract,
+            ProposalKind::CancelPaymentStream { stream_id: id },
+        );
+        assert_eq!(
+            contract.internal_reserved_amount(OLD_BASE_TOKEN),
+            to_yocto("20")
+        );
+        context.block_timestamp(300 * DAY);
+        testing_env!(context.build());
+        assert_eq!(
+            contract.get_payment_stream(id).claimable,
+            U128(to_yocto("20"))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_STREAM_RECEIVER")]
+    fn test_payment_stream_claim_by_other() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .account_balance(to_yocto("1000"))
+            .build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1)]),
+        );
+        let id = execute(
+            &mut context,
+            &mut contract,
+            ProposalKind::CreatePaymentStream {
+                token_id: String::from(OLD_BASE_TOKEN),
+                receiver_id: accounts(2),
+                amount_per_period: U128(to_yocto("10")),
+                period: U64(DAY),
+                start: U64(0),
+                end: U64(10 * DAY),
+                cliff: U64(0),
+            },
+        );
+        context.block_timestamp(5 * DAY);
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.stream_claim(id);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ENOUGH_FUNDS")]
+    fn test_payment_stream_not_enough_funds() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .account_balance(to_yocto("100"))
+            .build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1)]),
+        );
+        execute(
+            &mut context,
+            &mut contract,
+            ProposalKind::CreatePaymentStream {
+                token_id: String::from(OLD_BASE_TOKEN),
+                receiver_id: accounts(2),
+                amount_per_period: U128(to_yocto("10")),
+                period: U64(DAY),
+                start: U64(0),
+                end: U64(20 * DAY),
+                cliff: U64(0),
+            },
+        );
+    }
+}
@@ -0,0 +1,412 @@
+//! Token swaps of the treasury on Ref Exchange.
+//! Tokens are deposited to the exchange, swapped with a single pool and the proceeds are withdrawn back to the DAO.
+
+use near_contract_standards::fungible_token::core_impl::ext_fungible_token;
+use near_contract_standards::storage_management::StorageBalance;
+use near_sdk::serde_json::{self, json};
+use near_sdk::PromiseOrValue;
+
+use crate::treasury::ext_storage_management;
+use crate::types::{
+    GAS_FOR_REF_DEPOSIT, GAS_FOR_REF_REGISTER, GAS_FOR_REF_SWAP, GAS_FOR_REF_WITHDRAW,
+    GAS_FOR_STORAGE_BALANCE_OF, GAS_FOR_SWAP_CALLBACK, GAS_FOR_SWAP_DEPOSIT_CALLBACK,
+    GAS_FOR_SWAP_REGISTER_CALLBACK, GAS_FOR_SWAP_STORAGE_CALLBACK, GAS_FOR_SWAP_WITHDRAW_CALLBACK,
+    ONE_YOCTO_NEAR, REF_STORAGE_DEPOSIT,
+};
+use crate::*;
+
+/// Swap of `amount_in` of `token_in` to at least `min_amount_out` of `token_out`
+/// with the pool `pool_id` of the Ref Exchange deployed at `exchange_id`.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct Swap {
+    pub exchange_id: AccountId,
+    pub pool_id: u64,
+    pub token_in: AccountId,
+    pub amount_in: U128,
+    pub token_out: AccountId,
+    pub min_amount_out: U128,
+}
+
+/// Step of the swap whose result `on_swap_callback` receives.
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub enum SwapStep {
+    /// Storage balance of the DAO with the exchange, to register it only if it isn't yet.
+    StorageBalance,
+    /// Registration of the DAO and the tokens with the exchange.
+    Register,
+    /// Deposit of `token_in` to the exchange.
+    Deposit,
+    /// Swap with the pool.
+    Swap,
+    /// Withdrawal of the proceeds if the swap succeeded, otherwise of the deposited tokens.
+    Withdraw { swapped: bool },
+}
+
+impl Swap {
+    /// Panics if the swap is not valid.
+    pub(crate) fn assert_valid(&self) {
+        assert!(self.amount_in.0 > 0, "ERR_ZERO_AMOUNT");
+        assert_ne!(self.token_in, self.token_out, "ERR_INVALID_SWAP");
+    }
+
+    /// Calls `on_swap_callback` with the result of the given step.
+    fn callback(&self, step: SwapStep) -> Promise {
+        let gas = match step {
+            SwapStep::StorageBalance => GAS_FOR_SWAP_STORAGE_CALLBACK,
+            SwapStep::Register => GAS_FOR_SWAP_REGISTER_CALLBACK,
+            SwapStep::Deposit => GAS_FOR_SWAP_DEPOSIT_CALLBACK,
+            SwapStep::Swap => GAS_FOR_SWAP_CALLBACK,
+            SwapStep::Withdraw { .. } => GAS_FOR_SWAP_WITHDRAW_CALLBACK,
+        };
+        ext_self::on_swap_callback(self.clone(), step, env::current_account_id(), 0, gas)
+    }
+
+    /// Requests the storage balance of the DAO with the exchange.
+    fn storage_balance(&self) -> Promise {
+        ext_storage_management::storage_balance_of(
+            env::current_account_id(),
+            self.exchange_id.clone(),
+            0,
+            GAS_FOR_STORAGE_BALANCE_OF,
+        )
+    }
+
+    /// Registers both tokens with the exchange, and the DAO itself unless it's already `registered`.
+    fn register(&self, registered: bool) -> Promise {
+        let mut promise = Promise::new(self.exchange_id.clone());
+        if !registered {
+            // The deposit covers the storage of the DAO's account with the exchange, the rest stays there.
+            promise = promise.function_call(
+                "storage_deposit".to_string(),
+                json!({}).to_string().into_bytes(),
+                REF_STORAGE_DEPOSIT,
+                GAS_FOR_REF_REGISTER,
+            );
+        }
+        promise.function_call(
+            "register_tokens".to_string(),
+            json!({ "token_ids": [self.token_in, self.token_out] })
+                .to_string()
+                .into_bytes(),
+            0,
+            GAS_FOR_REF_REGISTER,
+        )
+    }
+
+    /// Deposits `amount_in` of `token_in` to the exchange.
+    fn deposit(&self) -> Promise {
+        ext_fungible_token::ft_transfer_call(
+            self.exchange_id.clone(),
+            self.amount_in,
+            None,
+            String::new(),
+            self.token_in.clone(),
+            ONE_YOCTO_NEAR,
+            GAS_FOR_REF_DEPOSIT,
+        )
+    }
+
+    /// Swaps the deposited tokens with the pool, failing if `min_amount_out` isn't met.
+    fn swap(&self) -> Promise {
+        let actions = json!({
+            "actions": [{
+                "pool_id": self.pool_id,
+                "token_in": self.token_in,
+                "amount_in": self.amount_in,
+                "token_out": self.token_out,
+                "min_amount_out": self.min_amount_out,
+            }],
+        });
+        Promise::new(self.exchange_id.clone()).function_call(
+            "swap".to_string(),
+            actions.to_string().into_bytes(),
+            ONE_YOCTO_NEAR,
+            GAS_FOR_REF_SWAP,
+        )
+    }
+
+    /// Withdraws `amount` of the token from the exchange back to the DAO.
+    fn withdraw(&self, token_id: &AccountId, amount: U128) -> Promise {
+        Promise::new(self.exchange_id.clone()).function_call(
+            "withdraw".to_string(),
+            json!({ "token_id": token_id, "amount": amount, "unregister": false })
+                .to_string()
+                .into_bytes(),
+            ONE_YOCTO_NEAR,
+            GAS_FOR_REF_WITHDRAW,
+        )
+    }
+}
+
+impl Contract {
+    /// Registers the DAO and both tokens with the exchange, then deposits `token_in`, swaps it
+    /// and withdraws the tokens, each step started by `on_swap_callback` once the previous one succeeded.
+    /// The chain resolves to whether the swap succeeded and its proceeds were withdrawn.
+    pub(crate) fn internal_swap(&self, swap: &Swap) -> Promise {
+        swap.storage_balance()
+            .then(swap.callback(SwapStep::StorageBalance))
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Receiving callback after each step of the swap on the exchange, which starts the next step.
+    /// If the swap fails (e.g. `min_amount_out` isn't met), the deposited tokens are withdrawn instead of the proceeds.
+    /// Resolves to false unless the swap succeeded and the proceeds were withdrawn,
+    /// so the proposal is marked as failed otherwise. Withdrawn proceeds refresh the cached balance of `token_out`.
+    #[private]
+    pub fn on_swap_callback(&mut self, swap: Swap, step: SwapStep) -> PromiseOrValue<bool> {
+        assert_eq!(
+            env::promise_results_count(),
+            1,
+            "ERR_UNEXPECTED_CALLBACK_PROMISES"
+        );
+        let result = match env::promise_result(0) {
+            PromiseResult::Successful(value) => Some(value),
+            _ => None,
+        };
+        match (step, result) {
+            (SwapStep::StorageBalance, Some(value)) => {
+                let storage_balance: Option<StorageBalance> =
+                    serde_json::from_slice(&value).expect("ERR_INVALID_STORAGE_BALANCE");
+                swap.register(storage_balance.is_some())
+                    .then(swap.callback(SwapStep::Register))
+                    .into()
+            }
+            (SwapStep::Register, Some(_)) => {
+                swap.deposit().then(swap.callback(SwapStep::Deposit)).into()
+            }
+            (SwapStep::Deposit, Some(value)) => {
+                // `ft_transfer_call` resolves to the amount the exchange kept.
+                let used: U128 =
+                    serde_json::from_slice(&value).expect("ERR_INVALID_DEPOSIT_RESULT");
+                if used == swap.amount_in {
+                    swap.swap().then(swap.callback(SwapStep::Swap)).into()
+                } else if used.0 > 0 {
+                    swap.withdraw(&swap.token_in, used)
+                        .then(swap.callback(SwapStep::Withdraw { swapped: false }))
+                        .into()
+                } else {
+                    PromiseOrValue::Value(false)
+                }
+            }
+            (SwapStep::Swap, Some(value)) => {
+                let amount_out: U128 =
+                    serde_json::from_slice(&value).expect("ERR_INVALID_SWAP_RESULT");
+                self.internal_token_withdrawn(&swap.token_in.to_string(), swap.amount_in.0);
+                swap.withdraw(&swap.token_out, amount_out)
+                    .then(swap.callback(SwapStep::Withdraw { swapped: true }))
+                    .into()
+            }
+            (SwapStep::Swap, None) => swap
+                .withdraw(&swap.token_in, swap.amount_in)
+                .then(swap.callback(SwapStep::Withdraw { swapped: false }))
+                .into(),
+            (SwapStep::Withdraw { swapped }, Some(_)) => {
+                if swapped {
+                    self.ft_refresh_balance(swap.token_out);
+                }
+                PromiseOrValue::Value(swapped)
+            }
+            // Nothing was deposited, or the tokens are left with the exchange.
+            (_, None) => PromiseOrValue::Value(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::mock::VmAction;
+    use near_sdk::test_utils::{accounts, get_created_receipts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    use crate::test_utils::promise_result;
+
+    use super::*;
+
+    fn swap() -> Swap {
+        Swap {
+            exchange_id: accounts(3),
+            pool_id: 0,
+            token_in: accounts(4),
+            amount_in: U128(100),
+            token_out: accounts(5),
+            min_amount_out: U128(90),
+        }
+    }
+
+    fn callback(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+        step: SwapStep,
+        result: PromiseResult,
+    ) -> PromiseOrValue<bool> {
+        promise_result(context, result);
+        contract.on_swap_callback(swap(), step)
+    }
+
+    /// Function calls scheduled by the last call, with their arguments.
+    fn created_calls() -> Vec<(String, serde_json::Value)> {
+        get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| receipt.actions)
+            .filter_map(|action| match action {
+                VmAction::FunctionCall {
+                    function_name,
+                    args,
+                    ..
+                } => Some((function_name, serde_json::from_slice(&args).unwrap())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn called(name: &str) -> serde_json::Value {
+        created_calls()
+            .into_iter()
+            .find_map(|(function_name, args)| (function_name == name).then(|| args))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_swap_steps() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context
+            .current_account_id(accounts(0))
+            .predecessor_account_id(accounts(1))
+            .build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1)]),
+        );
+        let success = |value: &[u8]| PromiseResult::Successful(value.to_vec());
+        let function_names = || {
+            created_calls()
+                .into_iter()
+                .map(|(function_name, _)| function_name)
+                .collect::<Vec<_>>()
+        };
+
+        // The DAO is registered with the exchange only if it isn't yet.
+        callback(
+            &mut context,
+            &mut contract,
+            SwapStep::StorageBalance,
+            success(b"null"),
+        );
+        assert_eq!(
+            function_names(),
+            vec!["storage_deposit", "register_tokens", "on_swap_callback"]
+        );
+        callback(
+            &mut context,
+            &mut contract,
+            SwapStep::StorageBalance,
+            success(b"{\"total\":\"100\",\"available\":\"0\"}"),
+        );
+        assert_eq!(
+            function_names(),
+            vec!["register_tokens", "on_swap_callback"]
+        );
+        assert_eq!(called("on_swap_callback")["step"], json!("Register"));
+
+        callback(
+            &mut context,
+            &mut contract,
+            SwapStep::Register,
+            success(b""),
+        );
+        assert_eq!(called("on_swap_callback")["step"], json!("Deposit"));
+        called("ft_transfer_call");
+
+        callback(
+            &mut context,
+            &mut contract,
+            SwapStep::Deposit,
+            success(b"\"100\""),
+        );
+        assert_eq!(called("on_swap_callback")["step"], json!("Swap"));
+        assert_eq!(called("swap")["actions"][0]["min_amount_out"], json!("90"));
+
+        callback(
+            &mut context,
+            &mut contract,
+            SwapStep::Swap,
+            success(b"\"95\""),
+        );
+        assert_eq!(
+            called("withdraw"),
+            json!({ "token_id": accounts(5), "amount": "95", "unregister": false })
+        );
+        assert_eq!(
+            called("on_swap_callback")["step"],
+            json!({ "Withdraw": { "swapped": true } })
+        );
+
+        // Succeeds only once the proceeds are withdrawn, their cached balance is then refreshed.
+        let step = SwapStep::Withdraw { swapped: true };
+        let result = callback(&mut context, &mut contract, step, success(b""));
+        assert!(matches!(result, PromiseOrValue::Value(true)));
+        assert_eq!(
+            called("on_ft_balance_callback")["token_id"],
+            json!(accounts(5))
+        );
+        let result = callback(&mut context, &mut contract, step, PromiseResult::Failed);
+        assert!(matches!(result, PromiseOrValue::Value(false)));
+    }
+
+    #[test]
+    fn test_swap_failures() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context
+            .current_account_id(accounts(0))
+            .predecessor_account_id(accounts(1))
+            .build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1)]),
+        );
+
+        // Failed registration or refunded deposit end the swap right away.
+        let result = callback(
+            &mut context,
+            &mut contract,
+            SwapStep::Register,
+            PromiseResult::Failed,
+        );
+        assert!(matches!(result, PromiseOrValue::Value(false)));
+        assert!(created_calls().is_empty());
+        let result = callback(
+            &mut context,
+            &mut contract,
+            SwapStep::Deposit,
+            PromiseResult::Successful(b"\"0\"".to_vec()),
+        );
+        assert!(matches!(result, PromiseOrValue::Value(false)));
+        assert!(created_calls().is_empty());
+
+        // Slippage bound not met, deposited tokens go back to the DAO and the swap fails.
+        callback(
+            &mut context,
+            &mut contract,
+            SwapStep::Swap,
+            PromiseResult::Failed,
+        );
+        assert_eq!(
+            called("withdraw"),
+            json!({ "token_id": accounts(4), "amount": "100", "unregister": false })
+        );
+        let step = SwapStep::Withdraw { swapped: false };
+        let result = callback(
+            &mut context,
+            &mut contract,
+            step,
+            PromiseResult::Successful(vec![]),
+        );
+        assert!(matches!(result, PromiseOrValue::Value(false)));
+    }
+}
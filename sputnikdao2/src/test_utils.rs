@@ -0,0 +1,49 @@
+//! Helpers shared by the unit tests of the modules.
+
+use near_sdk::test_utils::{accounts, VMContextBuilder};
+use near_sdk::{testing_env, PromiseResult};
+use near_sdk_sim::to_yocto;
+
+use crate::*;
+
+/// Adds a proposal of given kind by the council member `accounts(1)` and approves it.
+/// Returns the id of the proposal.
+pub(crate) fn execute(
+    context: &mut VMContextBuilder,
+    contract: &mut Contract,
+    kind: ProposalKind,
+) -> u64 {
+    testing_env!(context
+        .predecessor_account_id(accounts(1))
+        .attached_deposit(to_yocto("1"))
+        .build());
+    let id = contract.add_proposal(ProposalInput {
+        description: "test".to_string(),
+        kind,
+    });
+    testing_env!(context.attached_deposit(0).build());
+    contract.act_proposal(id, Action::VoteApprove, None);
+    id
+}
+
+/// Sets up the context of a callback that receives given result of the promise.
+pub(crate) fn promise_result(context: &VMContextBuilder, result: PromiseResult) {
+    testing_env!(
+        context.build(),
+        near_sdk::VMConfig::test(),
+        near_sdk::RuntimeFeesConfig::test(),
+        Default::default(),
+        vec![result],
+    );
+}
+
+/// Resolves the execution of the proposal with given result of its promise.
+pub(crate) fn resolve_proposal(
+    context: &VMContextBuilder,
+    contract: &mut Contract,
+    id: u64,
+    result: PromiseResult,
+) {
+    promise_result(context, result);
+    contract.on_proposal_callback(id);
+}
@@ -0,0 +1,520 @@
+//! Registry of fungible tokens held by the DAO.
+//! Balances are cached: deposits via `ft_transfer_call` and payouts of proposals keep them up to date,
+//! other movements (e.g. by function calls or refunds of `ft_transfer_call`) need `ft_refresh_balance`.
+
+use near_contract_standards::fungible_token::core_impl::ext_fungible_token;
+use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+use near_contract_standards::storage_management::StorageBalance;
+use near_sdk::json_types::U64;
+use near_sdk::{log, serde_json, PromiseOrValue};
+
+use crate::events::Event;
+use crate::types::{
+    convert_old_to_new_token, FT_STORAGE_DEPOSIT, GAS_FOR_FT_BALANCE_CALLBACK,
+    GAS_FOR_FT_BALANCE_OF, GAS_FOR_FT_TRANSFER, GAS_FOR_STORAGE_BALANCE_OF,
+    GAS_FOR_STORAGE_CALLBACK, GAS_FOR_STORAGE_DEPOSIT, ONE_YOCTO_NEAR,
+};
+use crate::*;
+
+/// Storage management (NEP-145) of the tokens.
+#[ext_contract(ext_storage_management)]
+pub trait StorageManagement {
+    fn storage_deposit(&mut self, account_id: Option<AccountId>, registration_only: Option<bool>);
+
+    fn storage_balance_of(&self, account_id: AccountId);
+}
+
+/// Cached balance of a fungible token held by the DAO.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenBalance {
+    pub balance: U128,
+    /// Last time the balance was changed or refreshed.
+    pub updated_at: U64,
+}
+
+/// Token payout waiting for the check if the receiver is registered on the token.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FtPayout {
+    pub token_id: AccountId,
+    pub receiver_id: AccountId,
+    pub amount: U128,
+    pub memo: String,
+    pub msg: Option<String>,
+}
+
+/// Transfers the tokens to `receiver_id`, with `ft_transfer_call` if `msg` is given.
+pub(crate) fn internal_ft_transfer(
+    token_id: &AccountId,
+    receiver_id: &AccountId,
+    amount: Balance,
+    memo: String,
+    msg: Option<String>,
+) -> Promise {
+    if let Some(msg) = msg {
+        ext_fungible_token::ft_transfer_call(
+            receiver_id.clone(),
+            U128(amount),
+            Some(memo),
+            msg,
+            token_id.clone(),
+            ONE_YOCTO_NEAR,
+            GAS_FOR_FT_TRANSFER,
+        )
+    } else {
+        ext_fungible_token::ft_transfer(
+            receiver_id.clone(),
+            U128(amount),
+            Some(memo),
+            token_id.clone(),
+            ONE_YOCTO_NEAR,
+            GAS_FOR_FT_TRANSFER,
+        )
+    }
+}
+
+impl Contract {
+    /// Checks if `receiver_id` is registered on the token before transferring the tokens.
+    /// The chain resolves to the result of the transfer, see `on_storage_balance_callback`.
+    pub(crate) fn internal_registered_ft_transfer(
+        &self,
+        token_id: &AccountId,
+        receiver_id: &AccountId,
+        amount: Balance,
+        memo: String,
+        msg: Option<String>,
+    ) -> Promise {
+        ext_storage_management::storage_balance_of(
+            receiver_id.clone(),
+            token_id.clone(),
+            0,
+            GAS_FOR_STORAGE_BALANCE_OF,
+        )
+        .then(ext_self::on_storage_balance_callback(
+            FtPayout {
+                token_id: token_id.clone(),
+                receiver_id: receiver_id.clone(),
+                amount: U128(amount),
+                memo,
+                msg,
+            },
+            env::current_account_id(),
+            0,
+            GAS_FOR_STORAGE_CALLBACK,
+        ))
+    }
+
+    /// Tokens already tracked or listed in `treasury_tokens` of the policy.
+    /// Others are not registered, so any contract can't fill the registry.
+    fn internal_is_treasury_token(&self, token_id: &AccountId) -> bool {
+        self.tokens.get(token_id).is_some()
+            || self
+                .policy
+                .get()
+                .unwrap()
+                .to_policy()
+                .treasury_tokens
+                .contains(token_id)
+    }
+
+    /// Sets cached balance of the token, registering it if needed.
+    fn internal_set_token_balance(&mut self, token_id: &AccountId, balance: Balance) {
+        self.tokens.insert(
+            token_id,
+            &TokenBalance {
+                balance: U128(balance),
+                updated_at: U64(env::block_timestamp()),
+            },
+        );
+    }
+
+    /// Reduces cached balance of the token after it was paid out. Does nothing for $NEAR.
+    pub(crate) fn internal_token_withdrawn(&mut self, token_id: &OldAccountId, amount: Balance) {
+        let token_id = match convert_old_to_new_token(token_id) {
+            Some(token_id) => token_id,
+            None => return,
+        };
+        if let Some(token) = self.tokens.get(&token_id) {
+            self.internal_set_token_balance(&token_id, token.balance.0.saturating_sub(amount));
+        }
+    }
+
+    /// Amount of the token reserved for bounties, payment streams, vesting schedules and bulk transfers.
+    pub(crate) fn internal_reserved_amount(&self, token_id: &str) -> Balance {
+        self.reserves.get(&token_id.to_string()).unwrap_or_default()
+    }
+
+    /// Reserves amount of the token, so it's not counted as available.
+    pub(crate) fn internal_reserve(&mut self, token_id: &OldAccountId, amount: Balance) {
+        let reserved = self.internal_reserved_amount(token_id) + amount;
+        self.reserves.insert(token_id, &reserved);
+    }
+
+    /// Releases reserved amount of the token once it's paid out or no longer owed.
+    /// Releasing more than is reserved only clears the reserve, the mismatch is logged.
+    pub(crate) fn internal_release(&mut self, token_id: &OldAccountId, amount: Balance) {
+        let reserved = self.internal_reserved_amount(token_id);
+        if amount > reserved {
+            log!(
+                "Released {} of token {} but only {} is reserved",
+                amount,
+                token_id,
+                reserved
+            );
+        }
+        let reserved = reserved.saturating_sub(amount);
+        if reserved == 0 {
+            self.reserves.remove(token_id);
+        } else {
+            self.reserves.insert(token_id, &reserved);
+        }
+    }
+
+    /// Amount of the token that is neither locked nor reserved.
+    /// Only tracked tokens are counted, with their cached balance.
+    pub(crate) fn internal_available_amount(&self, token_id: &OldAccountId) -> Balance {
+        match convert_old_to_new_token(token_id) {
+            None => self.get_available_amount().0,
+            Some(token_id) => self
+                .tokens
+                .get(&token_id)
+                .map(|token| token.balance.0)
+                .unwrap_or_default()
+                .saturating_sub(self.internal_reserved_amount(token_id.as_str())),
+        }
+    }
+
+    /// Asserts the amount of the token can be spent without using funds reserved for others.
+    /// Tokens with nothing reserved aren't checked, as the cached balance can be outdated
+    /// and a payout larger than the balance fails anyway.
+    pub(crate) fn assert_spendable(&self, token_id: &OldAccountId, amount: Balance) {
+        assert!(
+            self.internal_reserved_amount(token_id) == 0
+                || self.internal_available_amount(token_id) >= amount,
+            "ERR_NOT_ENOUGH_FUNDS"
+        );
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Requests the balance of this DAO from the given token and updates the cached one.
+    /// Anyone can call this. Tokens listed in `treasury_tokens` of the policy get registered.
+    pub fn ft_refresh_balance(&mut self, token_id: AccountId) -> Promise {
+        ext_fungible_token::ft_balance_of(
+            env::current_account_id(),
+            token_id.clone(),
+            0,
+            GAS_FOR_FT_BALANCE_OF,
+        )
+        .then(ext_self::on_ft_balance_callback(
+            token_id,
+            env::current_account_id(),
+            0,
+            GAS_FOR_FT_BALANCE_CALLBACK,
+        ))
+    }
+
+    /// Receiving callback with the balance of the DAO in the token.
+    #[private]
+    pub fn on_ft_balance_callback(&mut self, token_id: AccountId) -> U128 {
+        assert_eq!(
+            env::promise_results_count(),
+            1,
+            "ERR_UNEXPECTED_CALLBACK_PROMISES"
+        );
+        let balance: U128 = match env::promise_result(0) {
+            PromiseResult::Successful(value) => {
+                serde_json::from_slice(&value).expect("ERR_INVALID_FT_BALANCE")
+            }
+            _ => env::panic_str("ERR_FT_BALANCE_FAILED"),
+        };
+        if self.internal_is_treasury_token(&token_id) {
+            self.internal_set_token_balance(&token_id, balance.0);
+        }
+        balance
+    }
+
+    /// Receiving callback with the storage balance of the receiver of a payout.
+    /// If the receiver is not registered on the token, pays `storage_deposit` for it first.
+    /// Fails if the storage balance can't be fetched, so the payout fails too.
+    #[private]
+    pub fn on_storage_balance_callback(&mut self, payout: FtPayout) -> Promise {
+        assert_eq!(
+            env::promise_results_count(),
+            1,
+            "ERR_UNEXPECTED_CALLBACK_PROMISES"
+        );
+        let storage_balance: Option<StorageBalance> = match env::promise_result(0) {
+            PromiseResult::Successful(value) => {
+                serde_json::from_slice(&value).expect("ERR_INVALID_STORAGE_BALANCE")
+            }
+            _ => env::panic_str("ERR_STORAGE_BALANCE_FAILED"),
+        };
+        let registered = storage_balance.is_some();
+        let transfer = |payout: FtPayout| {
+            internal_ft_transfer(
+                &payout.token_id,
+                &payout.receiver_id,
+                payout.amount.0,
+                payout.memo,
+                payout.msg,
+            )
+        };
+        if registered {
+            return transfer(payout);
+        }
+        ext_storage_management::storage_deposit(
+            Some(payout.receiver_id.clone()),
+            Some(true),
+            payout.token_id.clone(),
+            FT_STORAGE_DEPOSIT,
+            GAS_FOR_STORAGE_DEPOSIT,
+        )
+        .then(transfer(payout))
+    }
+}
+
+#[near_bindgen]
+impl FungibleTokenReceiver for Contract {
+    /// Accepts any token sent to the DAO. Only treasury tokens are added to the treasury,
+    /// others are kept without being tracked, as with a plain `ft_transfer`.
+    fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        let _ = msg;
+        let token_id = env::predecessor_account_id();
+        if !self.internal_is_treasury_token(&token_id) {
+            log!("Token {} is not tracked in the treasury", token_id);
+            return PromiseOrValue::Value(U128(0));
+        }
+        let balance = self
+            .tokens
+            .get(&token_id)
+            .map(|token| token.balance.0)
+            .unwrap_or_default();
+        self.internal_set_token_balance(&token_id, balance + amount.0);
+        Event::TokenDeposited {
+            token_id: &token_id,
+            sender_id: &sender_id,
+            amount,
+        }
+        .emit();
+        PromiseOrValue::Value(U128(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::mock::VmAction;
+    use near_sdk::test_utils::{accounts, get_created_receipts, VMContextBuilder};
+    use near_sdk::{testing_env, PromiseResult};
+    use near_sdk_sim::to_yocto;
+
+    use crate::test_utils::promise_result;
+    use crate::views::TreasuryOutput;
+
+    use super::*;
+
+    /// Function calls scheduled by the last call, with their deposits.
+    fn created_calls() -> Vec<(String, Balance)> {
+        get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| receipt.actions)
+            .filter_map(|action| match action {
+                VmAction::FunctionCall {
+                    function_name,
+                    deposit,
+                    ..
+                } => Some((function_name, deposit)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_treasury() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context
+            .current_account_id(accounts(0))
+            .predecessor_account_id(accounts(1))
+            .account_balance(to_yocto("1000"))
+            .build());
+        let mut policy = VersionedPolicy::Default(vec![accounts(1)]).upgrade();
+        policy.to_policy_mut().treasury_tokens = vec![accounts(3)];
+        let mut contract = Contract::new(Config::test_config(), policy);
+
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.ft_on_transfer(accounts(1), U128(100), String::new());
+        contract.ft_on_transfer(accounts(2), U128(50), String::new());
+        // Tokens that are not listed in the policy are not tracked.
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128(100), String::new());
+        contract.internal_add_bounty(&Bounty {
+            description: "test".to_string(),
+            token: accounts(3).to_string(),
+            amount: U128(10),
+            times: 3,
+            max_deadline: U64(0),
+            milestones: vec![],
+            assignment: None,
+            paused: false,
+        });
+        let near = contract.get_available_amount();
+        assert_eq!(
+            contract.get_treasury(0, 10),
+            vec![
+                TreasuryOutput {
+                    token_id: String::from(OLD_BASE_TOKEN),
+                    balance: near,
+                    committed: U128(0),
+                    available: near,
+                    staked: U128(0),
+                    updated_at: None,
+                },
+                TreasuryOutput {
+                    token_id: accounts(3).to_string(),
+                    balance: U128(150),
+                    committed: U128(30),
+                    available: U128(120),
+                    staked: U128(0),
+                    updated_at: Some(U64(0)),
+                },
+            ]
+        );
+
+        // Paying out part of the tokens is reflected, then refreshed balance replaces the cache.
+        contract.internal_token_withdrawn(&accounts(3).to_string(), 20);
+        assert_eq!(contract.get_treasury(1, 1)[0].balance, U128(130));
+        promise_result(
+            context
+                .predecessor_account_id(accounts(0))
+                .block_timestamp(10),
+            PromiseResult::Successful(b"\"500\"".to_vec()),
+        );
+        contract.on_ft_balance_callback(accounts(3));
+        let treasury = contract.get_treasury(1, 1);
+        assert_eq!(treasury[0].balance, U128(500));
+        assert_eq!(treasury[0].available, U128(470));
+        assert_eq!(treasury[0].updated_at, Some(U64(10)));
+
+        // Refreshing a token that is not listed in the policy doesn't register it.
+        promise_result(&context, PromiseResult::Successful(b"\"100\"".to_vec()));
+        contract.on_ft_balance_callback(accounts(4));
+        assert_eq!(contract.get_treasury(0, 10).len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ENOUGH_FUNDS")]
+    fn test_transfer_reserved_funds() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context
+            .current_account_id(accounts(0))
+            .predecessor_account_id(accounts(1))
+            .build());
+        let mut policy = VersionedPolicy::Default(vec![accounts(1)]).upgrade();
+        policy.to_policy_mut().treasury_tokens = vec![accounts(3)];
+        let mut contract = Contract::new(Config::test_config(), policy);
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.ft_on_transfer(accounts(1), U128(150), String::new());
+        contract.internal_add_bounty(&Bounty {
+            description: "test".to_string(),
+            token: accounts(3).to_string(),
+            amount: U128(50),
+            times: 2,
+            max_deadline: U64(0),
+            milestones: vec![],
+            assignment: None,
+            paused: false,
+        });
+
+        let mut transfer = |context: &mut VMContextBuilder, amount: Balance| {
+            testing_env!(context
+                .predecessor_account_id(accounts(1))
+                .attached_deposit(to_yocto("1"))
+                .build());
+            let id = contract.add_proposal(ProposalInput {
+                description: "test".to_string(),
+                kind: ProposalKind::Transfer {
+                    token_id: accounts(3).to_string(),
+                    receiver_id: accounts(2),
+                    amount: U128(amount),
+                    msg: None,
+                },
+            });
+            testing_env!(context.attached_deposit(0).build());
+            contract.act_proposal(id, Action::VoteApprove, None);
+        };
+        // Only the part that is not reserved for the bounty can be spent.
+        transfer(&mut context, 50);
+        transfer(&mut context, 100);
+    }
+
+    #[test]
+    fn test_register_token_receivers() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context
+            .current_account_id(accounts(0))
+            .predecessor_account_id(accounts(1))
+            .build());
+        let mut policy = VersionedPolicy::Default(vec![accounts(1)]).upgrade();
+        policy.to_policy_mut().register_token_receivers = true;
+        let mut contract = Contract::new(Config::test_config(), policy);
+        testing_env!(context.attached_deposit(to_yocto("1")).build());
+        let id = contract.add_proposal(ProposalInput {
+            description: "test".to_string(),
+            kind: ProposalKind::Transfer {
+                token_id: accounts(3).to_string(),
+                receiver_id: accounts(2),
+                amount: U128(100),
+                msg: None,
+            },
+        });
+        testing_env!(context.attached_deposit(0).build());
+        contract.act_proposal(id, Action::VoteApprove, None);
+        assert_eq!(
+            created_calls(),
+            vec![
+                ("storage_balance_of".to_string(), 0),
+                ("on_storage_balance_callback".to_string(), 0),
+                ("on_proposal_callback".to_string(), 0),
+            ]
+        );
+
+        // Receiver is not registered: the DAO pays the storage first.
+        let payout = || FtPayout {
+            token_id: accounts(3),
+            receiver_id: accounts(2),
+            amount: U128(100),
+            memo: "test".to_string(),
+            msg: None,
+        };
+        let callback = |context: &mut VMContextBuilder, result: &[u8]| {
+            promise_result(
+                context.predecessor_account_id(accounts(0)),
+                PromiseResult::Successful(result.to_vec()),
+            );
+        };
+        callback(&mut context, b"null");
+        contract.on_storage_balance_callback(payout());
+        assert_eq!(
+            created_calls(),
+            vec![
+                ("storage_deposit".to_string(), FT_STORAGE_DEPOSIT),
+                ("ft_transfer".to_string(), ONE_YOCTO_NEAR),
+            ]
+        );
+
+        callback(&mut context, br#"{"total":"1250","available":"0"}"#);
+        contract.on_storage_balance_callback(payout());
+        assert_eq!(
+            created_calls(),
+            vec![("ft_transfer".to_string(), ONE_YOCTO_NEAR)]
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::json_types::Base64VecU8;
+use near_sdk::json_types::{Base64VecU8, U128};
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::{AccountId, Balance, Gas};
 
@@ -17,6 +17,87 @@ pub const ONE_YOCTO_NEAR: Balance = 1;
 /// Gas for single ft_transfer call.
 pub const GAS_FOR_FT_TRANSFER: Gas = Gas(10_000_000_000_000);
 
+/// Gas for single nft_transfer call.
+pub const GAS_FOR_NFT_TRANSFER: Gas = Gas(15_000_000_000_000);
+
+/// Gas for nft_transfer_call, including the receiver's `nft_on_transfer` and resolving the transfer.
+pub const GAS_FOR_NFT_TRANSFER_CALL: Gas = Gas(50_000_000_000_000);
+
+/// Gas for calls to a staking pool.
+pub const GAS_FOR_STAKING_POOL: Gas = Gas(50_000_000_000_000);
+
+/// Gas for `storage_balance_of` call on a token.
+pub const GAS_FOR_STORAGE_BALANCE_OF: Gas = Gas(5_000_000_000_000);
+
+/// Gas for `storage_deposit` call on a token.
+pub const GAS_FOR_STORAGE_DEPOSIT: Gas = Gas(10_000_000_000_000);
+
+/// Gas for the callback that registers the receiver if needed and transfers the tokens.
+pub const GAS_FOR_STORAGE_CALLBACK: Gas = Gas(30_000_000_000_000);
+
+/// Deposit attached to `storage_deposit` for a receiver of tokens.
+/// Registration only is requested, so tokens refund what is above their minimum.
+pub const FT_STORAGE_DEPOSIT: Balance = 12_500_000_000_000_000_000_000;
+
+/// Gas for `ft_balance_of` call on a token.
+pub const GAS_FOR_FT_BALANCE_OF: Gas = Gas(5_000_000_000_000);
+
+/// Gas for the callback with the balance of a token.
+pub const GAS_FOR_FT_BALANCE_CALLBACK: Gas = Gas(5_000_000_000_000);
+
+/// Gas reserved per row of a bulk transfer, enough for a token payout that registers the receiver.
+pub const GAS_FOR_BULK_TRANSFER_ROW: Gas = Gas(45_000_000_000_000);
+
+/// Gas for the callback after the payout of a bulk transfer row.
+pub const GAS_FOR_BULK_TRANSFER_CALLBACK: Gas = Gas(5_000_000_000_000);
+
+/// Gas for the callback after the payout of a payment stream claim.
+pub const GAS_FOR_STREAM_CLAIM_CALLBACK: Gas = Gas(10_000_000_000_000);
+
+/// Gas for the callback after the payout of a vesting claim.
+pub const GAS_FOR_VESTING_CLAIM_CALLBACK: Gas = Gas(10_000_000_000_000);
+
+/// Gas for registering with Ref Exchange, per call.
+pub const GAS_FOR_REF_REGISTER: Gas = Gas(10_000_000_000_000);
+
+/// Gas for depositing tokens to Ref Exchange with `ft_transfer_call`.
+pub const GAS_FOR_REF_DEPOSIT: Gas = Gas(50_000_000_000_000);
+
+/// Gas for a single pool swap on Ref Exchange.
+pub const GAS_FOR_REF_SWAP: Gas = Gas(20_000_000_000_000);
+
+/// Gas for withdrawing tokens from Ref Exchange, including its transfer and callback.
+pub const GAS_FOR_REF_WITHDRAW: Gas = Gas(50_000_000_000_000);
+
+/// Gas for the callback with the storage balance on Ref Exchange, which registers the DAO and the tokens.
+pub const GAS_FOR_SWAP_STORAGE_CALLBACK: Gas = Gas(180_000_000_000_000);
+
+/// Gas for the callback after registering with Ref Exchange, which deposits the tokens.
+pub const GAS_FOR_SWAP_REGISTER_CALLBACK: Gas = Gas(150_000_000_000_000);
+
+/// Gas for the callback after the deposit, which swaps the tokens.
+pub const GAS_FOR_SWAP_DEPOSIT_CALLBACK: Gas = Gas(95_000_000_000_000);
+
+/// Gas for the callback after the swap, which withdraws the tokens.
+pub const GAS_FOR_SWAP_CALLBACK: Gas = Gas(70_000_000_000_000);
+
+/// Gas for the callback after the withdrawal, which resolves the swap and refreshes the balance of the proceeds.
+pub const GAS_FOR_SWAP_WITHDRAW_CALLBACK: Gas = Gas(15_000_000_000_000);
+
+/// Deposit attached to `storage_deposit` on Ref Exchange before a swap.
+/// Covers the DAO's account and its tokens, what is left stays as storage balance of the DAO.
+pub const REF_STORAGE_DEPOSIT: Balance = 10_000_000_000_000_000_000_000;
+
+/// Gas for adding the moved proposal in the hub DAO.
+pub const GAS_FOR_MOVE_PROPOSAL: Gas = Gas(30_000_000_000_000);
+
+/// Gas for the callback after moving the proposal.
+pub const GAS_FOR_MOVE_CALLBACK: Gas = Gas(10_000_000_000_000);
+
+/// Gas for the callback after executing a proposal. Covers releasing the reserved funds and the
+/// payout records of a bounty, updating the treasury, staking pools or NFT inventory, and returning the bond.
+pub const GAS_FOR_PROPOSAL_CALLBACK: Gas = Gas(20_000_000_000_000);
+
 /// Configuration of the DAO.
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Debug)]
 #[serde(crate = "near_sdk::serde")]
@@ -59,12 +140,32 @@ pub enum Action {
     /// (or in the future can be used for early proposal closure).
     Finalize,
     /// Move a proposal to the hub to shift into another DAO.
-    MoveToHub,
+    MoveToHub { hub_id: AccountId },
+    /// Execute queued proposal after its execution delay has passed. Anyone can call it.
+    Execute,
+    /// Veto queued proposal before its execution delay has passed.
+    Veto,
+    /// Vote to abstain, counts towards participation quorum but not for any decision.
+    VoteAbstain,
+    /// Retract own vote while proposal is in progress, if policy allows changing votes.
+    RetractVote,
+    /// Vote on a poll with indices of chosen options.
+    /// Single option, any set of approved options or ranking by preference, depending on the poll.
+    CastBallot { ballot: Vec<u32> },
+    /// Stake given amount of own delegated weight behind a conviction funding proposal.
+    StakeConviction { amount: U128 },
+    /// Withdraw own stake from a conviction funding proposal.
+    UnstakeConviction,
 }
 
 impl Action {
     pub fn to_policy_label(&self) -> String {
-        format!("{:?}", self)
+        match self {
+            Action::MoveToHub { .. } => "MoveToHub".to_string(),
+            Action::CastBallot { .. } => "CastBallot".to_string(),
+            Action::StakeConviction { .. } => "StakeConviction".to_string(),
+            _ => format!("{:?}", self),
+        }
     }
 }
 
@@ -0,0 +1,321 @@
+//! Vesting schedules: grants of $NEAR or fungible tokens that vest over time and are claimed by the beneficiary.
+//! The granted amount is reserved in the treasury until it's claimed or the schedule is revoked.
+
+use near_sdk::json_types::U64;
+use near_sdk::PromiseOrValue;
+
+use crate::events::Event;
+use crate::types::{convert_old_to_new_token, GAS_FOR_VESTING_CLAIM_CALLBACK};
+use crate::*;
+
+/// How the granted amount vests.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub enum Vesting {
+    /// Vests linearly between `start` and `end`. Nothing can be claimed before `cliff`.
+    Linear { start: U64, cliff: U64, end: U64 },
+    /// Whole amount vests at `cliff`.
+    Cliff { cliff: U64 },
+}
+
+/// Grant of `amount` of `token_id` to `beneficiary_id`.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct VestingSchedule {
+    /// Can be "" for $NEAR or a valid account id.
+    pub token_id: OldAccountId,
+    pub beneficiary_id: AccountId,
+    /// Total amount granted.
+    pub amount: U128,
+    pub vesting: Vesting,
+    /// Amount already claimed by the beneficiary.
+    pub claimed: U128,
+    /// When the schedule was revoked. Nothing vests afterwards.
+    pub revoked_at: Option<U64>,
+}
+
+impl VestingSchedule {
+    /// New schedule, nothing claimed yet.
+    pub fn new(
+        token_id: &OldAccountId,
+        beneficiary_id: &AccountId,
+        amount: U128,
+        vesting: &Vesting,
+    ) -> Self {
+        Self {
+            token_id: token_id.clone(),
+            beneficiary_id: beneficiary_id.clone(),
+            amount,
+            vesting: vesting.clone(),
+            claimed: U128(0),
+            revoked_at: None,
+        }
+    }
+
+    /// Amount vested at given time.
+    pub fn vested(&self, timestamp: u64) -> Balance {
+        let timestamp = match self.revoked_at {
+            Some(revoked_at) => timestamp.min(revoked_at.0),
+            None => timestamp,
+        };
+        match &self.vesting {
+            Vesting::Linear { start, cliff, end } => {
+                if timestamp < cliff.0 {
+                    0
+                } else if timestamp >= end.0 {
+                    self.amount.0
+                } else {
+                    // Split the amount, so multiplying doesn't overflow with large amounts.
+                    let elapsed = (timestamp - start.0) as Balance;
+                    let duration = (end.0 - start.0) as Balance;
+                    self.amount.0 / duration * elapsed
+                        + self.amount.0 % duration * elapsed / duration
+                }
+            }
+            Vesting::Cliff { cliff } => {
+                if timestamp < cliff.0 {
+                    0
+                } else {
+                    self.amount.0
+                }
+            }
+        }
+    }
+
+    /// Amount the beneficiary can claim now.
+    pub fn claimable(&self) -> Balance {
+        self.vested(env::block_timestamp()) - self.claimed.0
+    }
+
+    /// Panics if the schedule is not valid.
+    pub(crate) fn assert_valid(&self) {
+        assert!(self.amount.0 > 0, "ERR_ZERO_AMOUNT");
+        if let Vesting::Linear { start, cliff, end } = &self.vesting {
+            assert!(
+                start.0 <= cliff.0 && cliff.0 <= end.0 && start.0 < end.0,
+                "ERR_INVALID_VESTING"
+            );
+        }
+    }
+}
+
+impl Contract {
+    /// Creates the schedule of the approved proposal, reserving the granted amount.
+    /// Fails if the treasury doesn't have enough of it available.
+    pub(crate) fn internal_create_vesting(&mut self, schedule_id: u64, schedule: &VestingSchedule) {
+        assert!(
+            self.internal_available_amount(&schedule.token_id) >= schedule.amount.0,
+            "ERR_NOT_ENOUGH_FUNDS"
+        );
+        self.internal_reserve(&schedule.token_id, schedule.amount.0);
+        self.vesting_schedules.insert(&schedule_id, schedule);
+        let mut ids = self
+            .beneficiary_vestings
+            .get(&schedule.beneficiary_id)
+            .unwrap_or_default();
+        ids.push(schedule_id);
+        self.beneficiary_vestings
+            .insert(&schedule.beneficiary_id, &ids);
+    }
+
+    /// Stops vesting now and returns the unvested amount to the treasury.
+    /// What has vested until now can still be claimed.
+    pub(crate) fn internal_revoke_vesting(&mut self, schedule_id: u64) {
+        let mut schedule = self
+            .vesting_schedules
+            .get(&schedule_id)
+            .expect("ERR_NO_VESTING");
+        assert!(schedule.revoked_at.is_none(), "ERR_VESTING_REVOKED");
+        let now = env::block_timestamp();
+        let unvested = schedule.amount.0 - schedule.vested(now);
+        schedule.revoked_at = Some(U64(now));
+        self.internal_release(&schedule.token_id, unvested);
+        self.vesting_schedules.insert(&schedule_id, &schedule);
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Pays out what has vested since the last claim. Only the beneficiary can call this.
+    pub fn vesting_claim(&mut self, schedule_id: u64) -> Promise {
+        let mut schedule = self
+            .vesting_schedules
+            .get(&schedule_id)
+            .expect("ERR_NO_VESTING");
+        assert_eq!(
+            env::predecessor_account_id(),
+            schedule.beneficiary_id,
+            "ERR_NOT_VESTING_BENEFICIARY"
+        );
+        let amount = schedule.claimable();
+        assert!(amount > 0, "ERR_NOTHING_TO_CLAIM");
+        schedule.claimed = U128(schedule.claimed.0 + amount);
+        self.vesting_schedules.insert(&schedule_id, &schedule);
+        self.internal_release(&schedule.token_id, amount);
+        let payout = match self.internal_payout(
+            &convert_old_to_new_token(&schedule.token_id),
+            &schedule.beneficiary_id,
+            amount,
+            format!("Vesting {}", schedule_id),
+            None,
+        ) {
+            PromiseOrValue::Promise(promise) => promise,
+            PromiseOrValue::Value(()) => unreachable!(),
+        };
+        payout.then(ext_self::on_vesting_claim_callback(
+            schedule_id,
+            U128(amount),
+            env::current_account_id(),
+            0,
+            GAS_FOR_VESTING_CLAIM_CALLBACK,
+        ))
+    }
+
+    /// Receiving callback after the payout of a vesting claim. Reverts the claim if the payout failed.
+    #[private]
+    pub fn on_vesting_claim_callback(&mut self, schedule_id: u64, amount: U128) {
+        assert_eq!(
+            env::promise_results_count(),
+            1,
+            "ERR_UNEXPECTED_CALLBACK_PROMISES"
+        );
+        let mut schedule = self
+            .vesting_schedules
+            .get(&schedule_id)
+            .expect("ERR_NO_VESTING");
+        match env::promise_result(0) {
+            PromiseResult::Successful(_) => {
+                self.internal_token_withdrawn(&schedule.token_id, amount.0);
+                Event::VestingClaimed {
+                    schedule_id,
+                    beneficiary_id: &schedule.beneficiary_id,
+                    amount,
+                }
+                .emit();
+            }
+            _ => {
+                schedule.claimed = U128(schedule.claimed.0 - amount.0);
+                self.vesting_schedules.insert(&schedule_id, &schedule);
+                self.internal_reserve(&schedule.token_id, amount.0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, PromiseResult};
+
+    use crate::test_utils::{execute, promise_result};
+
+    use super::*;
+
+    fn claim(context: &mut VMContextBuilder, contract: &mut Contract, id: u64) -> Balance {
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        let amount = contract.get_vesting_schedule(id).claimable;
+        contract.vesting_claim(id);
+        promise_result(context, PromiseResult::Successful(vec![]));
+        contract.on_vesting_claim_callback(id, amount);
+        amount.0
+    }
+
+    fn grant(vesting: Vesting) -> ProposalKind {
+        ProposalKind::CreateVestingSchedule {
+            token_id: accounts(4).to_string(),
+            beneficiary_id: accounts(2),
+            amount: U128(1_000),
+            vesting,
+        }
+    }
+
+    #[test]
+    fn test_vesting() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context
+            .current_account_id(accounts(0))
+            .predecessor_account_id(accounts(1))
+            .build());
+        let mut policy = VersionedPolicy::Default(vec![accounts(1)]).upgrade();
+        policy.to_policy_mut().treasury_tokens = vec![accounts(4)];
+        let mut contract = Contract::new(Config::test_config(), policy);
+        testing_env!(context.predecessor_account_id(accounts(4)).build());
+        contract.ft_on_transfer(accounts(1), U128(2_000), String::new());
+        let linear = execute(
+            &mut context,
+            &mut contract,
+            grant(Vesting::Linear {
+                start: U64(0),
+                cliff: U64(100),
+                end: U64(1_000),
+            }),
+        );
+        let cliff = execute(
+            &mut context,
+            &mut contract,
+            grant(Vesting::Cliff { cliff: U64(500) }),
+        );
+        assert_eq!(
+            contract.internal_reserved_amount(&accounts(4).to_string()),
+            2_000
+        );
+
+        context.block_timestamp(99);
+        testing_env!(context.build());
+        assert_eq!(contract.get_vesting_schedule(linear).claimable, U128(0));
+        context.block_timestamp(250);
+        assert_eq!(claim(&mut context, &mut contract, linear), 250);
+        assert_eq!(contract.get_vesting_schedule(cliff).claimable, U128(0));
+
+        // Revoking returns the unvested amount, the vested one can still be claimed.
+        context.block_timestamp(400);
+        execute(
+            &mut context,
+            &mut contract,
+            ProposalKind::RevokeVestingSchedule {
+                schedule_id: linear,
+            },
+        );
+        assert_eq!(
+            contract.internal_reserved_amount(&accounts(4).to_string()),
+            150 + 1_000
+        );
+        context.block_timestamp(600);
+        assert_eq!(claim(&mut context, &mut contract, linear), 150);
+        assert_eq!(claim(&mut context, &mut contract, cliff), 1_000);
+        assert_eq!(
+            contract.internal_reserved_amount(&accounts(4).to_string()),
+            0
+        );
+
+        let schedules = contract.get_vesting_schedules(accounts(2));
+        assert_eq!(
+            schedules.iter().map(|s| s.id).collect::<Vec<_>>(),
+            vec![linear, cliff]
+        );
+        assert_eq!(schedules[0].schedule.claimed, U128(400));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ENOUGH_FUNDS")]
+    fn test_vesting_not_enough_funds() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context
+            .current_account_id(accounts(0))
+            .predecessor_account_id(accounts(1))
+            .build());
+        let mut contract = Contract::new(
+            Config::test_config(),
+            VersionedPolicy::Default(vec![accounts(1)]),
+        );
+        // The token isn't tracked, so the treasury has none of it available.
+        execute(
+            &mut context,
+            &mut contract,
+            grant(Vesting::Cliff { cliff: U64(500) }),
+        );
+    }
+}
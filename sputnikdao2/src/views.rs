@@ -1,4 +1,5 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U64;
 
 use std::cmp::min;
 
@@ -24,6 +25,134 @@ pub struct BountyOutput {
     pub bounty: Bounty,
 }
 
+/// This is format of output via JSON for the claim of a bounty.
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct BountyClaimOutput {
+    /// Account that claimed the bounty.
+    pub account_id: AccountId,
+    #[serde(flatten)]
+    pub claim: BountyClaim,
+    /// Time after which the claim is expired, unless it's completed.
+    pub expires_at: U64,
+    /// Is the claim past its deadline without being completed?
+    /// Then anyone can free up its slot with `bounty_done`.
+    pub expired: bool,
+}
+
+/// This is format of output via JSON for the conviction of a proposal.
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct ConvictionOutput {
+    /// Weight currently staked behind the proposal.
+    pub staked: U128,
+    /// Conviction as of now.
+    pub conviction: U128,
+    /// Conviction needed to pass with the current treasury. None if the request is too large.
+    pub threshold: Option<U128>,
+}
+
+/// This is format of output via JSON for the spending limit of a role.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SpendingLimitOutput {
+    /// Name of the role.
+    pub role: String,
+    /// Limit of the role, own or the policy's one.
+    pub limit: SpendingLimit,
+    /// Amount paid out by transfers in the current period of the limit.
+    pub spent: U128,
+}
+
+/// This is format of output via JSON for the treasury balance of a token.
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct TreasuryOutput {
+    /// Token id, "" for $NEAR.
+    pub token_id: OldAccountId,
+    /// Balance of the DAO. For $NEAR, excludes locked bonds and storage.
+    pub balance: U128,
+    /// Amount that open bounties and payment streams can still pay out.
+    pub committed: U128,
+    /// Balance not committed to bounties, payment streams or vesting schedules.
+    pub available: U128,
+    /// $NEAR deposited with staking pools, which is not part of the balance. Zero for tokens.
+    pub staked: U128,
+    /// When the cached balance was last updated. None for $NEAR, which is always current.
+    pub updated_at: Option<U64>,
+}
+
+/// This is format of output via JSON for the NFTs held of a contract.
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftOutput {
+    pub nft_contract_id: AccountId,
+    pub token_ids: Vec<TokenId>,
+}
+
+/// This is format of output via JSON for the deposit with a staking pool.
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct StakingPoolOutput {
+    pub pool_id: AccountId,
+    #[serde(flatten)]
+    pub deposit: PoolDeposit,
+}
+
+/// This is format of output via JSON for the payment stream.
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct PaymentStreamOutput {
+    /// Id of the proposal that created the stream.
+    pub id: u64,
+    #[serde(flatten)]
+    pub stream: PaymentStream,
+    /// Amount the receiver can claim now.
+    pub claimable: U128,
+}
+
+impl PaymentStreamOutput {
+    fn from(id: u64, stream: PaymentStream) -> Self {
+        Self {
+            id,
+            claimable: U128(stream.claimable()),
+            stream,
+        }
+    }
+}
+
+/// This is format of output via JSON for the vesting schedule.
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct VestingScheduleOutput {
+    /// Id of the proposal that created the schedule.
+    pub id: u64,
+    #[serde(flatten)]
+    pub schedule: VestingSchedule,
+    /// Amount vested until now.
+    pub vested: U128,
+    /// Amount the beneficiary can claim now.
+    pub claimable: U128,
+}
+
+impl VestingScheduleOutput {
+    fn from(id: u64, schedule: VestingSchedule) -> Self {
+        Self {
+            id,
+            vested: U128(schedule.vested(env::block_timestamp())),
+            claimable: U128(schedule.claimable()),
+            schedule,
+        }
+    }
+}
+
 #[near_bindgen]
 impl Contract {
     /// Returns semver of this contract.
@@ -58,8 +187,13 @@ impl Contract {
     }
 
     /// Returns available amount of NEAR that can be spent (outside of amount for storage and bonds).
+    /// $NEAR deposited with staking pools is not included, see `get_staking_pools`.
+    /// $NEAR reserved for bounties, payment streams and vesting schedules is not available either.
     pub fn get_available_amount(&self) -> U128 {
-        U128(env::account_balance() - self.get_locked_storage_amount().0 - self.locked_amount)
+        U128(
+            (env::account_balance() - self.get_locked_storage_amount().0 - self.locked_amount)
+                .saturating_sub(self.internal_reserved_amount(OLD_BASE_TOKEN)),
+        )
     }
 
     /// Returns total delegated stake.
@@ -72,6 +206,11 @@ impl Contract {
         U128(self.delegations.get(&account_id).unwrap_or_default())
     }
 
+    /// Returns delegated stake to given account as of given checkpoint (proposal id).
+    pub fn delegation_balance_at(&self, account_id: AccountId, checkpoint_id: u64) -> U128 {
+        U128(self.get_user_weight_at(&account_id, checkpoint_id))
+    }
+
     /// Combines balance and total amount for calling from external contracts.
     pub fn delegation_balance_ratio(&self, account_id: AccountId) -> (U128, U128) {
         (
@@ -106,6 +245,165 @@ impl Contract {
         }
     }
 
+    /// Get current conviction of the conviction funding proposal.
+    pub fn get_conviction(&self, id: u64) -> ConvictionOutput {
+        let proposal: Proposal = self.proposals.get(&id).expect("ERR_NO_PROPOSAL").into();
+        assert!(
+            matches!(proposal.kind, ProposalKind::ConvictionFunding { .. }),
+            "ERR_NOT_CONVICTION_FUNDING"
+        );
+        let policy = self.policy.get().unwrap().to_policy();
+        let (staked, conviction) = proposal.conviction.as_ref().map_or((0, 0), |conviction| {
+            (
+                conviction.staked.0,
+                conviction.current(&policy.conviction, env::block_timestamp()),
+            )
+        });
+        ConvictionOutput {
+            staked: U128(staked),
+            conviction: U128(conviction),
+            threshold: self
+                .internal_conviction_threshold(&policy, &proposal)
+                .map(U128),
+        }
+    }
+
+    /// Get spending limits of all roles for given token ("" for $NEAR) and how much of them is spent.
+    pub fn get_spending_limits(&self, token_id: OldAccountId) -> Vec<SpendingLimitOutput> {
+        let policy = self.policy.get().unwrap().to_policy();
+        policy
+            .roles
+            .iter()
+            .filter_map(|role| {
+                let limit = role
+                    .spending_limits
+                    .get(&token_id)
+                    .or(policy.spending_limits.get(&token_id))?;
+                Some(SpendingLimitOutput {
+                    role: role.name.clone(),
+                    limit: limit.clone(),
+                    spent: U128(self.internal_spent_amount(&token_id, limit.period.0)),
+                })
+            })
+            .collect()
+    }
+
+    /// Get `limit` of treasury balances from given index: $NEAR first, then the tracked tokens.
+    pub fn get_treasury(&self, from_index: u64, limit: u64) -> Vec<TreasuryOutput> {
+        let output = |token_id: OldAccountId, balance: Balance, updated_at: Option<U64>| {
+            let committed = self.internal_reserved_amount(&token_id);
+            TreasuryOutput {
+                token_id,
+                balance: U128(balance),
+                committed: U128(committed),
+                available: U128(balance.saturating_sub(committed)),
+                staked: U128(0),
+                updated_at,
+            }
+        };
+        let near = TreasuryOutput {
+            staked: U128(self.internal_staked_amount()),
+            ..output(
+                String::from(OLD_BASE_TOKEN),
+                self.get_available_amount().0 + self.internal_reserved_amount(OLD_BASE_TOKEN),
+                None,
+            )
+        };
+        std::iter::once(near)
+            .chain(self.tokens.iter().map(|(token_id, token)| {
+                output(
+                    token_id.to_string(),
+                    token.balance.0,
+                    Some(token.updated_at),
+                )
+            }))
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .collect()
+    }
+
+    /// Get `limit` of staking pools from given index, with the $NEAR deposited with each.
+    pub fn get_staking_pools(&self, from_index: u64, limit: u64) -> Vec<StakingPoolOutput> {
+        self.staking_pools
+            .iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .map(|(pool_id, deposit)| StakingPoolOutput { pool_id, deposit })
+            .collect()
+    }
+
+    /// Get `limit` of rows of the bulk transfer proposal from given index, with their status.
+    pub fn get_bulk_transfer(
+        &self,
+        proposal_id: u64,
+        from_index: u64,
+        limit: u64,
+    ) -> Vec<BulkTransferRow> {
+        self.bulk_transfers
+            .get(&proposal_id)
+            .expect("ERR_NO_BULK_TRANSFER")
+            .into_iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .collect()
+    }
+
+    /// Get payment stream by id of the proposal that created it.
+    pub fn get_payment_stream(&self, id: u64) -> PaymentStreamOutput {
+        let stream = self
+            .payment_streams
+            .get(&id)
+            .expect("ERR_NO_PAYMENT_STREAM");
+        PaymentStreamOutput::from(id, stream)
+    }
+
+    /// Get `limit` of payment streams from given index, including cancelled and finished ones.
+    pub fn get_payment_streams(&self, from_index: u64, limit: u64) -> Vec<PaymentStreamOutput> {
+        self.payment_streams
+            .iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .map(|(id, stream)| PaymentStreamOutput::from(id, stream))
+            .collect()
+    }
+
+    /// Get vesting schedule by id of the proposal that created it.
+    pub fn get_vesting_schedule(&self, id: u64) -> VestingScheduleOutput {
+        let schedule = self.vesting_schedules.get(&id).expect("ERR_NO_VESTING");
+        VestingScheduleOutput::from(id, schedule)
+    }
+
+    /// Get all vesting schedules of the beneficiary, including revoked and fully claimed ones.
+    pub fn get_vesting_schedules(&self, beneficiary_id: AccountId) -> Vec<VestingScheduleOutput> {
+        self.beneficiary_vestings
+            .get(&beneficiary_id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|id| VestingScheduleOutput::from(id, self.vesting_schedules.get(&id).unwrap()))
+            .collect()
+    }
+
+    /// Get `limit` of NFT contracts from given index, with the token ids held of each.
+    pub fn get_nfts(&self, from_index: u64, limit: u64) -> Vec<NftOutput> {
+        self.nfts
+            .iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .map(|(nft_contract_id, token_ids)| NftOutput {
+                nft_contract_id,
+                token_ids: token_ids.to_vec(),
+            })
+            .collect()
+    }
+
+    /// Get token ids of NFTs held of given contract.
+    pub fn get_nft_tokens(&self, nft_contract_id: AccountId) -> Vec<TokenId> {
+        self.nfts
+            .get(&nft_contract_id)
+            .map(|token_ids| token_ids.to_vec())
+            .unwrap_or_default()
+    }
+
     /// Get given bounty by id.
     pub fn get_bounty(&self, id: u64) -> BountyOutput {
         let bounty = self.bounties.get(&id).expect("ERR_NO_BOUNTY");
@@ -137,6 +435,46 @@ impl Contract {
         self.bounty_claimers.get(&account_id).unwrap_or_default()
     }
 
+    /// Get `limit` of claims in progress for given bounty from given index.
+    /// Claims made before the claims were indexed by bounty are only included once added with `index_bounty_claims`.
+    pub fn get_bounty_claims_by_bounty(
+        &self,
+        id: u64,
+        from_index: u64,
+        limit: u64,
+    ) -> Vec<BountyClaimOutput> {
+        let now = env::block_timestamp();
+        self.bounty_claims_index
+            .get(&id)
+            .unwrap_or_default()
+            .into_iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .filter_map(|account_id| {
+                let claims = self.bounty_claimers.get(&account_id)?;
+                let claim = claims.into_iter().find(|claim| claim.bounty_id == id)?;
+                Some(BountyClaimOutput {
+                    expires_at: U64(claim.expires_at()),
+                    expired: !claim.completed && now > claim.expires_at(),
+                    account_id,
+                    claim,
+                })
+            })
+            .collect()
+    }
+
+    /// Get milestones of given bounty done so far by the claimer `account_id`.
+    pub fn get_bounty_claim_milestones(&self, id: u64, account_id: AccountId) -> Vec<u32> {
+        self.bounty_claim_milestones
+            .get(&(id, account_id))
+            .unwrap_or_default()
+    }
+
+    /// Get pending applications for given bounty.
+    pub fn get_bounty_applications(&self, id: u64) -> Vec<BountyApplication> {
+        self.bounty_applications.get(&id).unwrap_or_default()
+    }
+
     /// Returns number of claims per given bounty.
     pub fn get_bounty_number_of_claims(&self, id: u64) -> u32 {
         self.bounty_claims_count.get(&id).unwrap_or_default()
@@ -213,17 +213,33 @@ workspace.test('Proposal ChangePolicy', async (test, { alice, root, dao }) => {
                 },
                 permissions: ['*:VoteApprove', '*:AddProposal'],
                 vote_policy: {},
+                spending_limits: {},
             },
         ],
         default_vote_policy: {
             weight_kind: 'TokenWeight',
             quorum: new BN('1').toString(),
             threshold: '5',
+            quorum_kind: 'Decision',
+            combination: null,
         },
         proposal_bond: toYocto('1'),
         proposal_period: period,
         bounty_bond: toYocto('1'),
         bounty_forgiveness_period: period,
+        execution_delay: {},
+        allow_vote_change: false,
+        conviction: {
+            period: '86400000000000',
+            decay: [9, 10],
+            max_ratio: [1, 5],
+            weight: [1, 400],
+        },
+        spending_limits: {},
+        register_token_receivers: false,
+        moved_proposal_sources: [],
+        treasury_tokens: [],
+        treasury_nft_contracts: [],
     };
     let id: number = await alice.call(
         dao,
@@ -271,7 +287,7 @@ workspace.test('Proposal ChangePolicy', async (test, { alice, root, dao }) => {
     test.deepEqual(
         (await dao.view('get_proposals', { from_index: 0, limit: 10 }))[0]
             .vote_counts,
-        { council: [1, 0, 0] },
+        { council: [1, 0, 0, 0] },
     );
     test.is(
         (await dao.view('get_proposals', { from_index: 0, limit: 10 }))[0]
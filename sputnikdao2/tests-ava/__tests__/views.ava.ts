@@ -66,6 +66,7 @@ workspace.test('View method get_policy', async (test, { root }) => {
                 kind: 'Everyone',
                 permissions: ['*:AddProposal'],
                 vote_policy: {},
+                spending_limits: {},
             },
             {
                 name: 'council',
@@ -78,19 +79,37 @@ workspace.test('View method get_policy', async (test, { root }) => {
                     '*:VoteApprove',
                     '*:VoteReject',
                     '*:VoteRemove',
+                    '*:VoteAbstain',
+                    '*:CastBallot',
                 ],
                 vote_policy: {},
+                spending_limits: {},
             },
         ],
         default_vote_policy: {
             weight_kind: 'RoleWeight',
             quorum: '0',
             threshold: [1, 2],
+            quorum_kind: 'Decision',
+            combination: null,
         },
         proposal_bond: '1000000000000000000000000',
         proposal_period: '604800000000000',
         bounty_bond: '1000000000000000000000000',
         bounty_forgiveness_period: '86400000000000',
+        execution_delay: {},
+        allow_vote_change: false,
+        conviction: {
+            period: '86400000000000',
+            decay: [9, 10],
+            max_ratio: [1, 5],
+            weight: [1, 400],
+        },
+        spending_limits: {},
+        register_token_receivers: false,
+        moved_proposal_sources: [],
+        treasury_tokens: [],
+        treasury_nft_contracts: [],
     };
     test.deepEqual(await bob.view('get_policy'), policy);
 });
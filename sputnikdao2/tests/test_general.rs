@@ -8,9 +8,9 @@ use near_sdk_sim::{call, init_simulator, to_yocto, view};
 use crate::utils::*;
 use sputnik_staking::User;
 use sputnikdao2::{
-    default_policy, Action, BountyClaim, BountyOutput, Config, Policy, Proposal, ProposalInput,
-    ProposalKind, ProposalOutput, ProposalStatus, RoleKind, RolePermission, VersionedPolicy,
-    VotePolicy,
+    default_policy, Action, BountyClaim, BountyOutput, Config, ConvictionPolicy, Policy, Proposal,
+    ProposalInput, ProposalKind, ProposalOutput, ProposalStatus, RoleKind, RolePermission,
+    VersionedPolicy, VotePolicy,
 };
 
 mod utils;
@@ -63,6 +63,7 @@ fn test_large_policy() {
             .into_iter()
             .collect(),
             vote_policy: HashMap::default(),
+            spending_limits: HashMap::default(),
         };
         policy.add_or_update_role(&role);
     }
@@ -102,18 +103,21 @@ fn test_multi_council() {
                 kind: RoleKind::Everyone,
                 permissions: vec!["*:AddProposal".to_string()].into_iter().collect(),
                 vote_policy: HashMap::default(),
+                spending_limits: HashMap::default(),
             },
             RolePermission {
                 name: "council".to_string(),
                 kind: RoleKind::Group(vec![user(1), user(2)].into_iter().collect()),
                 permissions: vec!["*:*".to_string()].into_iter().collect(),
                 vote_policy: HashMap::default(),
+                spending_limits: HashMap::default(),
             },
             RolePermission {
                 name: "community".to_string(),
                 kind: RoleKind::Group(vec![user(1), user(3), user(4)].into_iter().collect()),
                 permissions: vec!["*:*".to_string()].into_iter().collect(),
                 vote_policy: HashMap::default(),
+                spending_limits: HashMap::default(),
             },
         ],
         default_vote_policy: VotePolicy::default(),
@@ -121,6 +125,14 @@ fn test_multi_council() {
         proposal_period: U64::from(1_000_000_000 * 60 * 60 * 24 * 7),
         bounty_bond: U128(10u128.pow(24)),
         bounty_forgiveness_period: U64::from(1_000_000_000 * 60 * 60 * 24),
+        execution_delay: HashMap::default(),
+        allow_vote_change: false,
+        conviction: ConvictionPolicy::default(),
+        spending_limits: HashMap::default(),
+        register_token_receivers: false,
+        moved_proposal_sources: vec![],
+        treasury_tokens: vec![],
+        treasury_nft_contracts: vec![],
     };
     add_proposal(
         &root,
@@ -224,7 +236,7 @@ fn test_bounty_workflow() {
 
     call!(
         user2,
-        dao.bounty_done(bounty_id, None, "Bounty is done".to_string()),
+        dao.bounty_done(bounty_id, None, "Bounty is done".to_string(), None),
         deposit = to_yocto("1")
     )
     .assert_success();
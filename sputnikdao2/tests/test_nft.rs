@@ -0,0 +1,86 @@
+use near_contract_standards::non_fungible_token::Token;
+use near_sdk::AccountId;
+use near_sdk_sim::{call, deploy, init_simulator, to_yocto, view, ContractAccount, UserAccount};
+use sputnikdao2::{
+    default_policy, Action, NftOutput, Proposal, ProposalInput, ProposalKind, ProposalStatus,
+    VersionedPolicy,
+};
+use test_nft::ContractContract as TestNftContract;
+
+mod utils;
+use crate::utils::*;
+
+near_sdk_sim::lazy_static_include::lazy_static_include_bytes! {
+    NFT_WASM_BYTES => "../test-nft/res/test_nft.wasm",
+}
+
+fn setup_nft(root: &UserAccount, nft_id: &str) -> ContractAccount<TestNftContract> {
+    deploy!(
+        contract: TestNftContract,
+        contract_id: nft_id.to_string(),
+        bytes: &NFT_WASM_BYTES,
+        signer_account: root,
+        deposit: to_yocto("200"),
+        init_method: new()
+    )
+}
+
+fn owner_of(nft: &ContractAccount<TestNftContract>, token_id: &str) -> AccountId {
+    view!(nft.nft_token(token_id.to_string()))
+        .unwrap_json::<Option<Token>>()
+        .unwrap()
+        .owner_id
+}
+
+/// Test receiving NFTs with `nft_transfer_call` and sending them out with a `TransferNft` proposal.
+#[test]
+fn test_nft_transfer_call() {
+    let root = init_simulator(None);
+    let nft = setup_nft(&root, "test_nft");
+    let other_nft = setup_nft(&root, "other_nft");
+    let mut policy = default_policy(vec![root.account_id()]);
+    policy.treasury_nft_contracts = vec![nft.account_id()];
+    let dao = setup_dao_with_policy(&root, VersionedPolicy::Current(policy));
+
+    for nft in [&nft, &other_nft] {
+        call!(root, nft.mint("1".to_string(), root.account_id())).assert_success();
+        call!(
+            root,
+            nft.nft_transfer_call(dao.account_id(), "1".to_string(), None, None, String::new()),
+            deposit = 1
+        )
+        .assert_success();
+        // The DAO keeps the token, whether it records it or not.
+        assert_eq!(owner_of(nft, "1"), dao.account_id());
+    }
+    // Only the NFT of the contract listed in the policy is recorded.
+    assert_eq!(
+        view!(dao.get_nfts(0, 10)).unwrap_json::<Vec<NftOutput>>(),
+        vec![NftOutput {
+            nft_contract_id: nft.account_id(),
+            token_ids: vec!["1".to_string()],
+        }]
+    );
+
+    let id = add_proposal(
+        &root,
+        &dao,
+        ProposalInput {
+            description: "send back".to_string(),
+            kind: ProposalKind::TransferNft {
+                nft_contract_id: nft.account_id(),
+                token_id: "1".to_string(),
+                receiver_id: root.account_id(),
+                msg: None,
+            },
+        },
+    )
+    .unwrap_json::<u64>();
+    call!(root, dao.act_proposal(id, Action::VoteApprove, None)).assert_success();
+    let proposal = view!(dao.get_proposal(id)).unwrap_json::<Proposal>();
+    assert_eq!(proposal.status, ProposalStatus::Approved);
+    assert_eq!(owner_of(&nft, "1"), root.account_id());
+    assert!(view!(dao.get_nft_tokens(nft.account_id()))
+        .unwrap_json::<Vec<String>>()
+        .is_empty());
+}
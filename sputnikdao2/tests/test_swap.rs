@@ -0,0 +1,163 @@
+use near_sdk::json_types::U128;
+use near_sdk::serde_json::{self, json};
+use near_sdk::AccountId;
+use near_sdk_sim::{call, deploy, to_yocto, view, ContractAccount, UserAccount, DEFAULT_GAS};
+use sputnikdao2::{Action, Proposal, ProposalInput, ProposalKind, ProposalStatus, Swap};
+use test_token::ContractContract as TestTokenContract;
+
+mod utils;
+use crate::utils::*;
+
+near_sdk_sim::lazy_static_include::lazy_static_include_bytes! {
+    TOKEN_WASM_BYTES => "../test-token/res/test_token.wasm",
+    REF_WASM_BYTES => "res/ref_exchange_release.wasm"
+}
+
+fn setup_token(root: &UserAccount, token_id: &str) -> ContractAccount<TestTokenContract> {
+    deploy!(
+        contract: TestTokenContract,
+        contract_id: token_id.to_string(),
+        bytes: &TOKEN_WASM_BYTES,
+        signer_account: root,
+        deposit: to_yocto("200"),
+        init_method: new()
+    )
+}
+
+fn call_ref(
+    root: &UserAccount,
+    ref_id: &AccountId,
+    method: &str,
+    args: serde_json::Value,
+    deposit: u128,
+) {
+    root.call(
+        ref_id.clone(),
+        method,
+        &args.to_string().into_bytes(),
+        DEFAULT_GAS,
+        deposit,
+    )
+    .assert_success();
+}
+
+fn add_swap_proposal(
+    root: &UserAccount,
+    dao: &ContractAccount<sputnikdao2::ContractContract>,
+    swap: Swap,
+) -> u64 {
+    add_proposal(
+        root,
+        dao,
+        ProposalInput {
+            description: "swap".to_string(),
+            kind: ProposalKind::Swap { swap },
+        },
+    )
+    .unwrap_json::<u64>()
+}
+
+/// Test swapping tokens of the DAO on Ref Exchange, failing if the slippage bound isn't met.
+#[test]
+fn test_swap_on_ref() {
+    let (root, dao) = setup_dao();
+    let token_a = setup_token(&root, "token_a");
+    let token_b = setup_token(&root, "token_b");
+    let ref_id: AccountId = "ref-finance".parse().unwrap();
+    root.deploy_and_init(
+        &REF_WASM_BYTES,
+        ref_id.clone(),
+        "new",
+        &json!({
+            "owner_id": root.account_id.clone(),
+            "exchange_fee": 4,
+            "referral_fee": 1,
+        })
+        .to_string()
+        .into_bytes(),
+        to_yocto("1000"),
+        DEFAULT_GAS,
+    );
+
+    // Pool with 1:1 liquidity provided by root.
+    for token in [&token_a, &token_b] {
+        call!(
+            root,
+            token.mint(root.account_id.clone(), U128(1_000_000_000))
+        )
+        .assert_success();
+        call!(
+            root,
+            token.storage_deposit(Some(ref_id.clone()), None),
+            deposit = to_yocto("1")
+        )
+        .assert_success();
+    }
+    call!(root, token_a.mint(dao.account_id(), U128(200_000))).assert_success();
+    call!(
+        root,
+        token_b.storage_deposit(Some(dao.account_id()), None),
+        deposit = to_yocto("1")
+    )
+    .assert_success();
+    let token_ids = json!([token_a.account_id(), token_b.account_id()]);
+    call_ref(&root, &ref_id, "storage_deposit", json!({}), to_yocto("1"));
+    call_ref(
+        &root,
+        &ref_id,
+        "add_simple_pool",
+        json!({ "tokens": token_ids, "fee": 25 }),
+        to_yocto("1"),
+    );
+    call_ref(
+        &root,
+        &ref_id,
+        "register_tokens",
+        json!({ "token_ids": token_ids }),
+        0,
+    );
+    for token in [&token_a, &token_b] {
+        call!(
+            root,
+            token.ft_transfer_call(ref_id.clone(), U128(1_000_000), None, String::new()),
+            deposit = 1
+        )
+        .assert_success();
+    }
+    call_ref(
+        &root,
+        &ref_id,
+        "add_liquidity",
+        json!({ "pool_id": 0, "amounts": [U128(1_000_000), U128(1_000_000)] }),
+        to_yocto("1"),
+    );
+
+    let swap = |min_amount_out: u128| Swap {
+        exchange_id: ref_id.clone(),
+        pool_id: 0,
+        token_in: token_a.account_id(),
+        amount_in: U128(100_000),
+        token_out: token_b.account_id(),
+        min_amount_out: U128(min_amount_out),
+    };
+    let balance = |token: &ContractAccount<TestTokenContract>| {
+        view!(token.ft_balance_of(dao.account_id()))
+            .unwrap_json::<U128>()
+            .0
+    };
+
+    // Swapping 100k returns about 90.7k, so the proposal fails and the tokens return to the DAO.
+    let id = add_swap_proposal(&root, &dao, swap(95_000));
+    call!(root, dao.act_proposal(id, Action::VoteApprove, None)).assert_success();
+    let proposal = view!(dao.get_proposal(id)).unwrap_json::<Proposal>();
+    assert_eq!(proposal.status, ProposalStatus::Failed);
+    assert_eq!(balance(&token_a), 200_000);
+    assert_eq!(balance(&token_b), 0);
+
+    let id = add_swap_proposal(&root, &dao, swap(90_000));
+    call!(root, dao.act_proposal(id, Action::VoteApprove, None)).assert_success();
+    let proposal = view!(dao.get_proposal(id)).unwrap_json::<Proposal>();
+    assert_eq!(proposal.status, ProposalStatus::Approved);
+    assert_eq!(balance(&token_a), 100_000);
+    assert!(balance(&token_b) >= 90_000);
+}
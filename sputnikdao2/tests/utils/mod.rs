@@ -47,20 +47,27 @@ pub fn setup_factory(root: &UserAccount) -> ContractAccount<FactoryContract> {
 
 pub fn setup_dao() -> (UserAccount, Contract) {
     let root = init_simulator(None);
+    let dao = setup_dao_with_policy(
+        &root,
+        VersionedPolicy::Default(vec![root.account_id.clone()]),
+    );
+    (root, dao)
+}
+
+pub fn setup_dao_with_policy(root: &UserAccount, policy: VersionedPolicy) -> Contract {
     let config = Config {
         name: "test".to_string(),
         purpose: "to test".to_string(),
         metadata: Base64VecU8(vec![]),
     };
-    let dao = deploy!(
+    deploy!(
         contract: DAOContract,
         contract_id: "dao".to_string(),
         bytes: &DAO_WASM_BYTES,
         signer_account: root,
         deposit: to_yocto("200"),
-        init_method: new(config, VersionedPolicy::Default(vec![root.account_id.clone()]))
-    );
-    (root, dao)
+        init_method: new(config, policy)
+    )
 }
 
 pub fn setup_test_token(root: &UserAccount) -> ContractAccount<TestTokenContract> {
@@ -147,6 +154,9 @@ pub fn add_bounty_proposal(root: &UserAccount, dao: &Contract) -> ExecutionResul
                     amount: U128(to_yocto("10")),
                     times: 3,
                     max_deadline: U64(env::block_timestamp() + 10_000_000_000),
+                    milestones: vec![],
+                    assignment: None,
+                    paused: false,
                 },
             },
         },
@@ -0,0 +1,5 @@
+#!/bin/bash
+set -e
+
+RUSTFLAGS='-C link-arg=-s' cargo +stable build --target wasm32-unknown-unknown --release
+cp ../target/wasm32-unknown-unknown/release/test_nft.wasm res/
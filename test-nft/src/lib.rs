@@ -0,0 +1,61 @@
+use near_contract_standards::non_fungible_token::{NonFungibleToken, Token, TokenId};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::{env, near_bindgen, AccountId, PanicOnDefault, PromiseOrValue};
+
+#[near_bindgen]
+#[derive(BorshSerialize, BorshDeserialize, PanicOnDefault)]
+pub struct Contract {
+    tokens: NonFungibleToken,
+}
+
+#[near_bindgen]
+impl Contract {
+    #[init]
+    pub fn new() -> Self {
+        Self {
+            tokens: NonFungibleToken::new(
+                b"o".to_vec(),
+                env::current_account_id(),
+                None::<Vec<u8>>,
+                None::<Vec<u8>>,
+                None::<Vec<u8>>,
+            ),
+        }
+    }
+
+    pub fn mint(&mut self, token_id: TokenId, account_id: AccountId) -> Token {
+        self.tokens
+            .internal_mint_with_refund(token_id, account_id, None, None)
+    }
+}
+
+near_contract_standards::impl_non_fungible_token_core!(Contract, tokens);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    #[test]
+    fn test_basics() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        contract.mint("1".to_string(), accounts(0));
+        assert_eq!(
+            contract.nft_token("1".to_string()).unwrap().owner_id,
+            accounts(0)
+        );
+
+        testing_env!(context
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(0))
+            .build());
+        contract.nft_transfer(accounts(1), "1".to_string(), None, None);
+        assert_eq!(
+            contract.nft_token("1".to_string()).unwrap().owner_id,
+            accounts(1)
+        );
+    }
+}